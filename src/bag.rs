@@ -0,0 +1,106 @@
+//! A bag of tiles to draw from, filled with the standard English set
+
+/// Letter used for blank tiles, which are drawn without a letter on them
+pub const BLANK: char = ' ';
+
+// How many of each letter there are in the standard 100-tile English set
+const DISTRIBUTION: [(char, usize); 27] = [
+    ('A', 9), ('B', 2), ('C', 2), ('D', 4), ('E', 12), ('F', 2), ('G', 3), ('H', 2), ('I', 9),
+    ('J', 1), ('K', 1), ('L', 4), ('M', 2), ('N', 6), ('O', 8), ('P', 2), ('Q', 1), ('R', 6),
+    ('S', 4), ('T', 6), ('U', 4), ('V', 2), ('W', 2), ('X', 1), ('Y', 2), ('Z', 1), (BLANK, 2),
+];
+
+/// The tiles that aren't on a rack or the board yet. Tiles come out in a random order, which is
+/// decided by the seed the bag is created with.
+pub struct TileBag {
+    letters: Vec<char>,
+    // State of the random number generator used to pick tiles
+    seed: u64,
+}
+
+impl TileBag {
+    /// Creates a bag holding the full standard set, shuffled according to `seed`
+    pub fn new(seed: u64) -> TileBag {
+        TileBag {
+            letters: DISTRIBUTION.iter()
+                .flat_map(|(letter, count)| std::iter::repeat_n(*letter, *count))
+                .collect(),
+            seed,
+        }
+    }
+
+    /// Number of tiles left in the bag
+    pub fn remaining(&self) -> usize {
+        self.letters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
+
+    /// Takes up to `n` random tiles out of the bag. Fewer come out if the bag runs out.
+    pub fn draw(&mut self, n: usize) -> Vec<char> {
+        (0..n.min(self.letters.len())).map(
+            |_| {
+                let index = (self.next_random() % self.letters.len() as u64) as usize;
+                self.letters.swap_remove(index)
+            }
+        ).collect()
+    }
+
+    /// Takes a particular letter out of the bag, e.g. for a tile that was dealt some other way.
+    /// Returns false if there are none of that letter left.
+    pub fn take(&mut self, letter: char) -> bool {
+        match self.letters.iter().position(|bag_letter| *bag_letter == letter) {
+            Some(index) => {
+                self.letters.swap_remove(index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns a tile to the bag
+    pub fn put_back(&mut self, letter: char) {
+        self.letters.push(letter);
+    }
+
+    fn next_random(&mut self) -> u64 {
+        // splitmix64
+        self.seed = self.seed.wrapping_add(0x9E3779B97F4A7C15);
+        let mut hash = self.seed;
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D049BB133111EB);
+        hash ^ (hash >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_draws_same_tiles() {
+        assert_eq!(TileBag::new(42).draw(7), TileBag::new(42).draw(7));
+    }
+
+    #[test]
+    fn drawn_tiles_can_be_put_back() {
+        let mut bag = TileBag::new(42);
+        let drawn = bag.draw(7);
+        assert_eq!(drawn.len(), 7);
+        assert_eq!(bag.remaining(), 93);
+        for letter in drawn {
+            bag.put_back(letter);
+        }
+        assert_eq!(bag.remaining(), 100);
+    }
+
+    #[test]
+    fn draw_stops_when_the_bag_runs_out() {
+        let mut bag = TileBag::new(42);
+        assert_eq!(bag.draw(200).len(), 100);
+        assert!(bag.is_empty());
+        assert!(!bag.take('A'));
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::Duration;
+use itertools::Itertools;
+
+// How much is kept, and how often and how small frames are captured to keep its memory in check
+const CAPTURE_LENGTH: Duration = Duration::from_secs(10);
+const CAPTURE_INTERVAL: Duration = Duration::from_millis(100);
+const CAPTURE_DOWNSCALE: usize = 4;
+
+struct Frame {
+    time: Duration,
+    width: u16,
+    height: u16,
+    // RGBA, row by row from the top
+    pixels: Vec<u8>,
+}
+
+/// Rolling buffer of what was on screen over the last CAPTURE_LENGTH, shrunk down, so the lead-up
+/// to something odd can be saved after it happens
+pub struct FrameCapture {
+    frames: VecDeque<Frame>,
+}
+
+impl FrameCapture {
+    pub fn new() -> FrameCapture {
+        FrameCapture {
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Grabs what's been drawn so far this frame, if it's time for another capture, and forgets
+    /// frames that are too old. Call it at the end of drawing, before presenting.
+    pub fn capture(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let now = ggez::timer::time_since_start(ctx);
+        if self.frames.back().is_some_and(|frame| now < frame.time + CAPTURE_INTERVAL) {
+            return Ok(());
+        }
+        while self.frames.front().is_some_and(|frame| now.saturating_sub(frame.time) > CAPTURE_LENGTH) {
+            self.frames.pop_front();
+        }
+
+        let screenshot = ggez::graphics::screenshot(ctx)?;
+        let full_pixels = screenshot.to_rgba8(ctx)?;
+        let full_width = screenshot.width() as usize;
+        let width = (full_width / CAPTURE_DOWNSCALE) as u16;
+        let height = (screenshot.height() as usize / CAPTURE_DOWNSCALE) as u16;
+        // Nearest neighbour is good enough to see what was going on
+        let pixels = (0..height as usize)
+            .cartesian_product(0..width as usize)
+            .flat_map(
+                |(y, x)| {
+                    let index = ((y * full_width + x) * CAPTURE_DOWNSCALE) * 4;
+                    full_pixels[index..index + 4].iter().copied()
+                }
+            )
+            .collect();
+        self.frames.push_back(Frame { time: now, width, height, pixels });
+        Ok(())
+    }
+
+    /// Saves the captured frames to `path` as a looping animated GIF, played back at the speed
+    /// they were captured
+    pub fn save(&self, path: &Path) -> image::ImageResult<()> {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let mut encoder = image::gif::GifEncoder::new(file);
+        encoder.set_repeat(image::gif::Repeat::Infinite)?;
+        for (index, frame) in self.frames.iter().enumerate() {
+            // Each frame stays up until the next one was captured
+            let delay = match self.frames.get(index + 1) {
+                Some(next_frame) => next_frame.time - frame.time,
+                None => CAPTURE_INTERVAL,
+            };
+            let buffer = image::RgbaImage::from_raw(frame.width as u32, frame.height as u32, frame.pixels.clone())
+                .expect("captured frames are always the size they say they are");
+            encoder.encode_frame(image::Frame::from_parts(buffer, 0, 0, image::Delay::from_saturating_duration(delay)))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// How many recent input events and tile changes are kept for the crash dump
+const INPUT_HISTORY: usize = 20;
+const CHANGE_HISTORY: usize = 20;
+const RACK_PREFIX: &str = "rack: ";
+
+// What the app was last doing, kept up to date by the app so the panic hook can dump it
+static RACK: Mutex<String> = Mutex::new(String::new());
+static RECENT_INPUT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static RECENT_CHANGES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// A crash dump left behind by the last run
+pub struct Report {
+    path: PathBuf,
+    rack: Option<String>,
+}
+
+impl Report {
+    /// Letters of the rack, in order, at the time of the crash
+    pub fn rack(&self) -> Option<&str> {
+        self.rack.as_deref()
+    }
+
+    /// Opens the folder containing the dump in the system file browser, so it can be attached
+    /// to a bug report
+    pub fn reveal(&self) {
+        let opener = if cfg!(target_os = "windows") {
+            "explorer"
+        } else if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        };
+        if let Some(folder) = self.path.parent() {
+            // Nothing useful to do if there's no file browser
+            let _ = std::process::Command::new(opener).arg(folder).spawn();
+        }
+    }
+}
+
+fn dump_path() -> PathBuf {
+    std::env::temp_dir().join("tile_rack_demo_crash.txt")
+}
+
+/// Sets up a panic hook that writes the rack, recent input, recent changes to the tiles and a
+/// backtrace to the dump file before the default hook runs
+pub fn install() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let mut dump = format!("{}\n\n", info);
+        // The panic may have happened while one of these was locked, so don't wait on them
+        if let Ok(rack) = RACK.try_lock() {
+            let _ = writeln!(dump, "{}{}", RACK_PREFIX, rack);
+        }
+        if let Ok(recent_input) = RECENT_INPUT.try_lock() {
+            dump += "\nRecent input:\n";
+            for input in recent_input.iter() {
+                let _ = writeln!(dump, "  {}", input);
+            }
+        }
+        if let Ok(recent_changes) = RECENT_CHANGES.try_lock() {
+            dump += "\nRecent changes:\n";
+            for change in recent_changes.iter() {
+                let _ = writeln!(dump, "  {}", change);
+            }
+        }
+        let _ = write!(dump, "\nBacktrace:\n{}", std::backtrace::Backtrace::force_capture());
+        let _ = std::fs::write(dump_path(), dump);
+        default_hook(info);
+    }));
+}
+
+/// Records the letters on the rack, in order
+pub fn record_rack(letters: &str) {
+    if let Ok(mut rack) = RACK.lock() {
+        if *rack != letters {
+            rack.clear();
+            rack.push_str(letters);
+        }
+    }
+}
+
+/// Records an input event, forgetting the oldest once there are more than INPUT_HISTORY
+pub fn record_input(input: String) {
+    if let Ok(mut recent_input) = RECENT_INPUT.lock() {
+        if recent_input.len() == INPUT_HISTORY {
+            recent_input.pop_front();
+        }
+        recent_input.push_back(input);
+    }
+}
+
+/// Records a change to the tiles, forgetting the oldest once there are more than CHANGE_HISTORY.
+/// Kept apart from the input so a burst of changes doesn't push out the input that caused it.
+pub fn record_change(change: String) {
+    if let Ok(mut recent_changes) = RECENT_CHANGES.lock() {
+        if recent_changes.len() == CHANGE_HISTORY {
+            recent_changes.pop_front();
+        }
+        recent_changes.push_back(change);
+    }
+}
+
+/// Returns the dump left by the last run, if it crashed. The dump is renamed so it's only
+/// reported once but can still be revealed.
+pub fn take_report() -> Option<Report> {
+    let contents = std::fs::read_to_string(dump_path()).ok()?;
+    let path = dump_path().with_extension("reported.txt");
+    std::fs::rename(dump_path(), &path).ok()?;
+    let rack = contents.lines()
+        .find_map(|line| line.strip_prefix(RACK_PREFIX))
+        .map(|rack| rack.to_owned());
+    Some(Report { path, rack })
+}
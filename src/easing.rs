@@ -0,0 +1,76 @@
+//! Curves for shaping how tiles move over the course of an animation
+
+use std::f32::consts::PI;
+
+/// Maps how far through an animation we are to how far the tile has moved, both from 0 to 1.
+/// Elastic and bounce overshoot or rebound along the way but still end at 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    /// Constant speed from start to finish
+    Linear,
+    /// Starts fast and slows down into place
+    EaseOutCubic,
+    /// Speeds up and then slows down
+    EaseInOut,
+    /// Overshoots and springs back into place
+    Elastic,
+    /// Bounces against its destination before settling
+    Bounce,
+}
+
+impl Easing {
+    /// Applies the curve to `t`, the fraction of the animation that's done
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            },
+            Easing::Elastic => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    2.0_f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+                }
+            },
+            Easing::Bounce => {
+                // Four parabolic hops, each smaller than the last
+                let (n, d) = (7.5625, 2.75);
+                if t < 1.0 / d {
+                    n * t * t
+                } else if t < 2.0 / d {
+                    let t = t - 1.5 / d;
+                    n * t * t + 0.75
+                } else if t < 2.5 / d {
+                    let t = t - 2.25 / d;
+                    n * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / d;
+                    n * t * t + 0.984375
+                }
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for Easing {
+    type Err = String;
+
+    /// Parses the curve's name in kebab case, e.g. `ease-out-cubic`
+    fn from_str(name: &str) -> Result<Easing, String> {
+        match name {
+            "linear" => Ok(Easing::Linear),
+            "ease-out-cubic" => Ok(Easing::EaseOutCubic),
+            "ease-in-out" => Ok(Easing::EaseInOut),
+            "elastic" => Ok(Easing::Elastic),
+            "bounce" => Ok(Easing::Bounce),
+            _ => Err(format!("unknown easing curve \"{}\"", name)),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use ggez::graphics::Color;
+use mint::Point2;
+
+/// Small glyphs drawn from meshes so they scale cleanly with the rest of the UI
+#[derive(Clone, Copy)]
+pub enum Icon {
+    Lock,
+    Unlock,
+    Sort,
+    Bag,
+    Exchange,
+    Designate,
+}
+
+impl Icon {
+    /// Draws the icon filling a `size` by `size` square with its top-left corner at `x`, `y`
+    pub fn draw(&self, ctx: &mut ggez::Context, x: f32, y: f32, size: f32, colour: Color) -> ggez::GameResult {
+        let stroke = ggez::graphics::DrawMode::stroke(size * 0.1);
+        // Everything is laid out on a unit square and scaled up to `size`
+        let point = |px: f32, py: f32| Point2 { x: x + px * size, y: y + py * size };
+        let mut mesh_builder = ggez::graphics::MeshBuilder::new();
+        match self {
+            Icon::Lock | Icon::Unlock => {
+                let body = ggez::graphics::Rect::new(x + 0.15 * size, y + 0.45 * size, 0.7 * size, 0.5 * size);
+                mesh_builder.rectangle(ggez::graphics::DrawMode::fill(), body, colour)?;
+                // An open lock has its shackle raised and only the right leg still in the body
+                let (lift, left_leg) = match self {
+                    Icon::Lock => (0.0, 0.45),
+                    _ => (0.1, 0.2),
+                };
+                let mut shackle = vec![point(0.3, left_leg)];
+                shackle.extend((0..=8).map(
+                    |segment| {
+                        let angle = std::f32::consts::PI * (1.0 + segment as f32 / 8.0);
+                        point(0.5 + 0.2 * angle.cos(), 0.3 - lift + 0.2 * angle.sin())
+                    }
+                ));
+                shackle.push(point(0.7, 0.45));
+                mesh_builder.polyline(stroke, &shackle, colour)?;
+            },
+            Icon::Sort => {
+                // Lines getting shorter, like a sorted list
+                for (row, length) in [0.8, 0.55, 0.3].iter().enumerate() {
+                    let line_y = 0.25 + row as f32 * 0.25;
+                    mesh_builder.line(&[point(0.1, line_y), point(0.1 + length, line_y)], size * 0.1, colour)?;
+                }
+            },
+            Icon::Bag => {
+                // A sack widening out from a tied neck
+                let sack = [point(0.35, 0.3), point(0.65, 0.3), point(0.9, 0.95), point(0.1, 0.95)];
+                mesh_builder.polygon(ggez::graphics::DrawMode::fill(), &sack, colour)?;
+                mesh_builder.line(&[point(0.3, 0.15), point(0.7, 0.15)], size * 0.1, colour)?;
+            },
+            Icon::Exchange => {
+                // Two arrows passing each other in opposite directions
+                mesh_builder.polyline(stroke, &[point(0.1, 0.3), point(0.9, 0.3), point(0.7, 0.1)], colour)?;
+                mesh_builder.polyline(stroke, &[point(0.9, 0.7), point(0.1, 0.7), point(0.3, 0.9)], colour)?;
+            },
+            Icon::Designate => {
+                // A pencil pointing down and to the left
+                mesh_builder.line(&[point(0.85, 0.15), point(0.35, 0.65)], size * 0.2, colour)?;
+                let tip = [point(0.28, 0.58), point(0.42, 0.72), point(0.1, 0.9)];
+                mesh_builder.polygon(ggez::graphics::DrawMode::fill(), &tip, colour)?;
+            },
+        }
+        let mesh = mesh_builder.build(ctx)?;
+        ggez::graphics::draw(ctx, &mesh, ggez::graphics::DrawParam::default())
+    }
+}
@@ -0,0 +1,1735 @@
+//! A Scrabble-style tile rack for ggez games. Tiles can be dragged around the rack with the
+//! mouse or moved with the keyboard, and slide into place when the order changes.
+//!
+//! Create a [`TileRack`], call its `update` method from your game's update loop and draw it like
+//! any other `Drawable`. Pass mouse events on with the `handle_mouse_*` methods, in the rack's
+//! coordinate space (i.e. before whatever `DrawParam` it's drawn with is applied).
+
+#![allow(unused_variables)]
+
+use std::cell::RefCell;
+use std::time::Duration;
+use ggez::graphics::Color;
+use mint::Point2;
+use itertools::Itertools;
+
+pub mod bag;
+pub mod easing;
+pub mod snapshot;
+
+use bag::TileBag;
+use easing::Easing;
+
+// Tile geometry used unless a rack or board's TileRackConfig says otherwise
+pub const TILE_WIDTH: f32 = 50.0;
+pub const TILE_HEIGHT: f32 = 50.0;
+pub const TILE_SPACING: f32 = 10.0;
+// Fraction of the remaining distance to the pointer that the dragged tile *doesn't* cover every
+// DRAG_SMOOTHING_PERIOD; 0 makes it stick to the pointer
+pub const DEFAULT_DRAG_SMOOTHING: f32 = 0.8;
+const TILE_COLOUR: Color = Color::new(0.9, 0.9, 0.9, 1.0);
+const LOCKED_TILE_COLOUR: Color = Color::new(0.7, 0.7, 0.7, 1.0);
+const FOCUS_RING_COLOUR: Color = Color::new(0.2, 0.4, 0.9, 1.0);
+const FOCUS_RING_WIDTH: f32 = 3.0;
+// Letters on blanks are written in this colour, so it's clear they're worth nothing
+const DESIGNATED_LABEL_COLOUR: Color = Color::new(0.75, 0.1, 0.1, 1.0);
+// Colours and outlines used instead of the usual ones in high-contrast mode
+const HIGH_CONTRAST_TILE_COLOUR: Color = Color::WHITE;
+const HIGH_CONTRAST_LOCKED_TILE_COLOUR: Color = Color::new(0.55, 0.55, 0.55, 1.0);
+const HIGH_CONTRAST_OUTLINE_WIDTH: f32 = 3.0;
+const DRAG_SMOOTHING_PERIOD: Duration = Duration::from_millis(2);
+// How long tiles take to slide into place (unless configured otherwise) and to fade in or out,
+// at normal animation speed
+const ANIMATION_DURATION: Duration = Duration::from_millis(200);
+const FADE_DURATION: Duration = Duration::from_millis(200);
+// Range of the animation speed multiplier; above the maximum, animations are skipped entirely
+const MIN_ANIMATION_SPEED: f32 = 0.25;
+const MAX_ANIMATION_SPEED: f32 = 4.0;
+// Largest rotation (in radians) and offset (in pixels) given to tiles when jitter is on
+const JITTER_ANGLE: f32 = 0.05;
+const JITTER_OFFSET: f32 = 2.0;
+const BOARD_CELL_COLOUR: Color = Color::new(0.93, 0.9, 0.82, 1.0);
+const BOARD_CELL_SPACING: f32 = 4.0;
+// How close to another tile's slot a dragged tile has to be dropped to swap with it
+const SWAP_RADIUS: f32 = 12.0;
+// How far tiles marked for exchange stick up out of the rack
+const EXCHANGE_LIFT: f32 = 12.0;
+const DEFAULT_LABEL_SIZE: f32 = 24.0;
+// Largest font size that still fits a letter on a tile
+const MAX_LABEL_SIZE: f32 = 40.0;
+// Word chunks grouped together by the chunk arrangement, in the order they're looked for
+const PREFIX_CHUNKS: [&str; 2] = ["UN", "RE"];
+const SUFFIX_CHUNKS: [&str; 4] = ["ING", "ED", "ER", "S"];
+
+/// Opacity that can be faded in or out over FADE_DURATION (at normal animation speed)
+#[derive(Clone, Copy)]
+struct Fade {
+    alpha: f32,
+    // Change in alpha per second; 0 when not fading
+    step: f32,
+}
+
+impl Fade {
+    fn new(alpha: f32) -> Fade {
+        Fade {
+            alpha,
+            step: 0.0,
+        }
+    }
+
+    fn fade_in(&mut self) {
+        self.step = 1.0 / FADE_DURATION.as_secs_f32();
+    }
+
+    fn fade_out(&mut self) {
+        self.step = -1.0 / FADE_DURATION.as_secs_f32();
+    }
+
+    fn is_fading(&self) -> bool {
+        self.step != 0.0
+    }
+
+    /// Advances the fade by `dt` seconds, which may be infinite to finish it straight away
+    fn update(&mut self, dt: f32) {
+        if self.is_fading() {
+            self.alpha = (self.alpha + self.step * dt).clamp(0.0, 1.0);
+            if self.alpha == 0.0 || self.alpha == 1.0 {
+                self.step = 0.0;
+            }
+        }
+    }
+}
+
+/// Sizes, colours and timing for the tiles on a rack, so different racks in the same game can
+/// look different. Start from the default and change what's needed, e.g.
+/// `TileRackConfig::default().with_tile_size(40.0, 40.0)`.
+#[derive(Clone, Copy)]
+pub struct TileRackConfig {
+    tile_width: f32,
+    tile_height: f32,
+    tile_spacing: f32,
+    tile_colour: Color,
+    locked_tile_colour: Color,
+    // How long tiles take to slide into their slots, at normal animation speed
+    animation_duration: Duration,
+}
+
+impl Default for TileRackConfig {
+    fn default() -> TileRackConfig {
+        TileRackConfig {
+            tile_width: TILE_WIDTH,
+            tile_height: TILE_HEIGHT,
+            tile_spacing: TILE_SPACING,
+            tile_colour: TILE_COLOUR,
+            locked_tile_colour: LOCKED_TILE_COLOUR,
+            animation_duration: ANIMATION_DURATION,
+        }
+    }
+}
+
+impl TileRackConfig {
+    pub fn with_tile_size(mut self, width: f32, height: f32) -> TileRackConfig {
+        self.tile_width = width;
+        self.tile_height = height;
+        self
+    }
+
+    /// Gap between neighbouring tiles
+    pub fn with_tile_spacing(mut self, spacing: f32) -> TileRackConfig {
+        self.tile_spacing = spacing;
+        self
+    }
+
+    /// Colour of unlocked tiles. High-contrast mode overrides it.
+    pub fn with_tile_colour(mut self, colour: Color) -> TileRackConfig {
+        self.tile_colour = colour;
+        self
+    }
+
+    /// Colour of locked tiles. High-contrast mode overrides it.
+    pub fn with_locked_tile_colour(mut self, colour: Color) -> TileRackConfig {
+        self.locked_tile_colour = colour;
+        self
+    }
+
+    /// How long tiles take to slide into their slots, before the rack's animation speed is applied
+    pub fn with_animation_duration(mut self, duration: Duration) -> TileRackConfig {
+        self.animation_duration = duration;
+        self
+    }
+
+    pub fn tile_width(&self) -> f32 {
+        self.tile_width
+    }
+
+    pub fn tile_height(&self) -> f32 {
+        self.tile_height
+    }
+
+    pub fn tile_spacing(&self) -> f32 {
+        self.tile_spacing
+    }
+}
+
+/// How the letter on a tile is written
+#[derive(Clone, Copy)]
+pub struct LabelStyle {
+    pub lowercase: bool,
+    /// In points; clamped so the letter still fits on the tile
+    pub font_size: f32,
+}
+
+impl Default for LabelStyle {
+    fn default() -> LabelStyle {
+        LabelStyle {
+            lowercase: false,
+            font_size: DEFAULT_LABEL_SIZE,
+        }
+    }
+}
+
+/// A single lettered tile. Tiles are created and owned by a `TileRack`.
+pub struct Tile {
+    // Stays the same however the tile is moved around, unlike its index in the rack
+    id: usize,
+    x: f32,
+    y: f32,
+    letter: char,
+    // Letter a blank stands for, once the player has chosen one
+    designation: Option<char>,
+    // Copied from the rack the tile was made for, and kept if it moves onto a board
+    config: TileRackConfig,
+    label_style: LabelStyle,
+    high_contrast: bool,
+    blend_mode: Option<ggez::graphics::BlendMode>,
+    locked: bool,
+    focused: bool,
+    marked_for_exchange: bool,
+    fade: Fade,
+    // Small per-tile imperfection so the rack looks hand-placed; all zero when jitter is off
+    jitter_angle: f32,
+    jitter_x: f32,
+    jitter_y: f32,
+    // Slide in progress, if any
+    animation: Option<Animation>,
+    // Built on first draw and reused, since draw only gets &self
+    meshes: RefCell<Option<TileMeshes>>,
+    label_cache: RefCell<Option<LabelCache>>,
+}
+
+/// A tile's shapes, built in white so they can be tinted by the DrawParam's colour when drawn
+struct TileMeshes {
+    body: ggez::graphics::Mesh,
+    outline: ggez::graphics::Mesh,
+    focus_ring: ggez::graphics::Mesh,
+    // Which style the focus ring was built for, since high contrast makes it thicker
+    high_contrast: bool,
+}
+
+/// A tile's letter laid out as text, kept until the letter or its size changes
+struct LabelCache {
+    label: char,
+    font_size: f32,
+    text: ggez::graphics::Text,
+    dimensions: ggez::graphics::Rect,
+}
+
+impl TileMeshes {
+    fn new(ctx: &mut ggez::Context, width: f32, height: f32, high_contrast: bool) -> ggez::GameResult<TileMeshes> {
+        let rect = ggez::graphics::Rect::new(0.0, 0.0, width, height);
+        let ring_width = if high_contrast { FOCUS_RING_WIDTH * 2.0 } else { FOCUS_RING_WIDTH };
+        // Drawn just outside the tile so it doesn't cover the letter
+        let mut ring_rect = rect;
+        ring_rect.x -= ring_width;
+        ring_rect.y -= ring_width;
+        ring_rect.w += ring_width * 2.0;
+        ring_rect.h += ring_width * 2.0;
+        Ok(TileMeshes {
+            body: ggez::graphics::Mesh::new_rectangle(ctx, ggez::graphics::DrawMode::fill(), rect, Color::WHITE)?,
+            outline: ggez::graphics::Mesh::new_rectangle(
+                ctx,
+                ggez::graphics::DrawMode::stroke(HIGH_CONTRAST_OUTLINE_WIDTH),
+                rect,
+                Color::WHITE,
+            )?,
+            focus_ring: ggez::graphics::Mesh::new_rectangle(
+                ctx,
+                ggez::graphics::DrawMode::stroke(ring_width),
+                ring_rect,
+                Color::WHITE,
+            )?,
+            high_contrast,
+        })
+    }
+}
+
+/// A tile sliding in a straight line from one place to another over its animation duration
+#[derive(Clone, Copy)]
+struct Animation {
+    from_x: f32,
+    from_y: f32,
+    to_x: f32,
+    to_y: f32,
+    // Seconds since it started, at normal animation speed
+    elapsed: f32,
+}
+
+impl Tile {
+    fn new(id: usize, x: f32, y: f32, letter: char, config: TileRackConfig) -> Tile {
+        Tile {
+            id,
+            x,
+            y,
+            letter,
+            designation: None,
+            config,
+            label_style: LabelStyle::default(),
+            high_contrast: false,
+            blend_mode: None,
+            locked: false,
+            focused: false,
+            marked_for_exchange: false,
+            fade: Fade::new(1.0),
+            jitter_angle: 0.0,
+            jitter_x: 0.0,
+            jitter_y: 0.0,
+            animation: None,
+            meshes: RefCell::new(None),
+            label_cache: RefCell::new(None),
+        }
+    }
+
+    /// Identifies the tile however it's moved around, unlike its index in the rack
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn letter(&self) -> char {
+        self.letter
+    }
+
+    /// Blanks have no letter of their own, but can be designated one with
+    /// `TileRack::designate_blank`
+    pub fn is_blank(&self) -> bool {
+        self.letter == bag::BLANK
+    }
+
+    /// Letter a blank has been designated, if any
+    pub fn designation(&self) -> Option<char> {
+        self.designation
+    }
+
+    /// x coordinate of the tile's top-left corner, in the rack's coordinate space
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    /// y coordinate of the tile's top-left corner, in the rack's coordinate space
+    pub fn y(&self) -> f32 {
+        self.y
+    }
+
+    /// Locked tiles can't be dragged and keep their slots when the rack is arranged
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Whether the tile will go back in the bag at the next `TileRack::exchange`
+    pub fn is_marked_for_exchange(&self) -> bool {
+        self.marked_for_exchange
+    }
+
+    fn set_pos(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    /// Moves the tile `dt` seconds further along its slide to `x`, `y`, following `easing`. If it
+    /// was heading somewhere else, it sets off afresh from where it is now.
+    fn animate_towards(&mut self, x: f32, y: f32, dt: f32, easing: Easing) {
+        if self.x == x && self.y == y {
+            self.animation = None;
+            return;
+        }
+        let mut animation = match self.animation {
+            Some(animation) if animation.to_x == x && animation.to_y == y => animation,
+            _ => Animation { from_x: self.x, from_y: self.y, to_x: x, to_y: y, elapsed: 0.0 },
+        };
+        animation.elapsed += dt;
+        let duration = self.config.animation_duration;
+        let time_fraction = animation.elapsed / duration.as_secs_f32();
+        // A zero duration means no slide at all, and would make the fraction NaN
+        if duration.is_zero() || time_fraction >= 1.0 {
+            self.set_pos(x, y);
+            self.animation = None;
+        } else {
+            let progress = easing.apply(time_fraction);
+            self.set_pos(
+                animation.from_x + (x - animation.from_x) * progress,
+                animation.from_y + (y - animation.from_y) * progress,
+            );
+            self.animation = Some(animation);
+        }
+    }
+
+    /// Sets the tile's jitter for the slot it's in. It's derived from the tile's id and slot so
+    /// that it stays put until the tile moves to a different slot.
+    fn set_jitter(&mut self, slot: usize, enabled: bool) {
+        if enabled {
+            let seed = ((self.id as u64) << 32) | slot as u64;
+            self.jitter_angle = JITTER_ANGLE * jitter_hash(seed, 0);
+            self.jitter_x = JITTER_OFFSET * jitter_hash(seed, 1);
+            self.jitter_y = JITTER_OFFSET * jitter_hash(seed, 2);
+        } else {
+            self.jitter_angle = 0.0;
+            self.jitter_x = 0.0;
+            self.jitter_y = 0.0;
+        }
+    }
+
+    /// DrawParam for drawing something at `x`, `y` relative to the tile's top-left corner, rotated
+    /// and offset along with the tile, and then transformed by `param`
+    fn get_local_param(&self, param: ggez::graphics::DrawParam, x: f32, y: f32) -> ggez::graphics::DrawParam {
+        // Rotate about the centre of the tile
+        let (sin, cos) = self.jitter_angle.sin_cos();
+        let (half_width, half_height) = (self.config.tile_width / 2.0, self.config.tile_height / 2.0);
+        let (dx, dy) = (x - half_width, y - half_height);
+        let dest = Point2 {
+            x: self.x + self.jitter_x + half_width + dx * cos - dy * sin,
+            y: self.y + self.jitter_y + half_height + dx * sin + dy * cos,
+        };
+        let local_param = ggez::graphics::DrawParam::default().dest(dest).rotation(self.jitter_angle);
+        compose_params(param, local_param)
+    }
+
+    fn colour(&self) -> Color {
+        match (self.locked, self.high_contrast) {
+            (false, false) => self.config.tile_colour,
+            (true, false) => self.config.locked_tile_colour,
+            (false, true) => HIGH_CONTRAST_TILE_COLOUR,
+            (true, true) => HIGH_CONTRAST_LOCKED_TILE_COLOUR,
+        }
+    }
+
+    /// The letter as it's written on the tile, which for a designated blank is its designation
+    fn label(&self) -> char {
+        let letter = self.designation.unwrap_or(self.letter);
+        if self.label_style.lowercase {
+            letter.to_ascii_lowercase()
+        } else {
+            letter.to_ascii_uppercase()
+        }
+    }
+
+    /// SVG elements for the tile with its top-left corner at `x`, `y`
+    fn svg_elements(&self, x: f32, y: f32) -> String {
+        let outline = if self.high_contrast {
+            format!(" stroke=\"#000000\" stroke-width=\"{}\"", HIGH_CONTRAST_OUTLINE_WIDTH)
+        } else {
+            String::new()
+        };
+        // Escape the few characters that mean something in XML, in case of unusual letters
+        let label = match self.label() {
+            '&' => "&amp;".to_owned(),
+            '<' => "&lt;".to_owned(),
+            '>' => "&gt;".to_owned(),
+            label => label.to_string(),
+        };
+        format!(
+            concat!(
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"{}/>\n",
+                "  <text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\">{}</text>\n",
+            ),
+            x, y, self.config.tile_width, self.config.tile_height, svg_colour(self.colour()), outline,
+            x + self.config.tile_width / 2.0, y + self.config.tile_height / 2.0, self.label_style.font_size,
+            svg_colour(self.label_colour()), label,
+        )
+    }
+
+    fn label_colour(&self) -> Color {
+        if self.designation.is_some() {
+            DESIGNATED_LABEL_COLOUR
+        } else {
+            Color::BLACK
+        }
+    }
+}
+
+/// Scales `dt` seconds by an animation speed multiplier. An infinite speed gives an infinite
+/// time step even if `dt` is 0, so that instant animations finish straight away.
+fn scale_time(dt: f32, speed: f32) -> f32 {
+    if speed.is_infinite() {
+        f32::INFINITY
+    } else {
+        dt * speed
+    }
+}
+
+/// `colour` written the way SVG expects, ignoring its alpha
+fn svg_colour(colour: Color) -> String {
+    let (r, g, b) = colour.to_rgb();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Wraps `elements` up as a standalone SVG image of the given size
+fn svg_document(width: f32, height: f32, elements: &str) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n{}</svg>\n",
+        width, height, elements,
+    )
+}
+
+/// Draws `rack` and `board` together as a standalone SVG image, laid out as they are on screen,
+/// with every tile in its slot or cell
+pub fn rack_and_board_to_svg(rack: &TileRack, board: &Board) -> String {
+    let left = rack.x.min(board.x);
+    let top = rack.y.min(board.y);
+    let right = (rack.x + rack.get_width()).max(board.x + board.get_width());
+    let bottom = (rack.y + rack.config.tile_height).max(board.y + board.get_height());
+    let elements = board.svg_elements(left, top) + &rack.svg_elements(left, top);
+    svg_document(right - left, bottom - top, &elements)
+}
+
+/// Places `local`, a DrawParam relative to some container, inside the container drawn with
+/// `parent`. This is what lets the rack and its tiles be moved, scaled and rotated as a whole.
+fn compose_params(parent: ggez::graphics::DrawParam, local: ggez::graphics::DrawParam) -> ggez::graphics::DrawParam {
+    let parent_matrix: [[f32; 4]; 4] = parent.trans.to_bare_matrix().into();
+    let local_matrix: [[f32; 4]; 4] = local.trans.to_bare_matrix().into();
+    // Both are column-major, so this is parent_matrix * local_matrix
+    let mut matrix = [[0.0; 4]; 4];
+    for (column, local_column) in matrix.iter_mut().zip(local_matrix.iter()) {
+        for (row, value) in column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| parent_matrix[k][row] * local_column[k]).sum();
+        }
+    }
+    local.transform(mint::ColumnMatrix4::from(matrix))
+}
+
+/// Hashes `seed` and `salt` to a number between -1 and 1. The same inputs always give the same
+/// output, which is what keeps jitter stable without storing random numbers.
+fn jitter_hash(seed: u64, salt: u64) -> f32 {
+    // splitmix64 finaliser
+    let mut hash = seed.wrapping_add(salt.wrapping_mul(0x9E3779B97F4A7C15));
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D049BB133111EB);
+    hash ^= hash >> 31;
+    (hash >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+}
+
+impl ggez::graphics::Drawable for Tile {
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        param: ggez::graphics::DrawParam,
+    ) -> ggez::GameResult {
+        let mut meshes = self.meshes.borrow_mut();
+        if meshes.as_ref().is_none_or(|meshes| meshes.high_contrast != self.high_contrast) {
+            *meshes = Some(TileMeshes::new(ctx, self.config.tile_width, self.config.tile_height, self.high_contrast)?);
+        }
+        let meshes = meshes.as_mut().unwrap();
+        let mut label_cache = self.label_cache.borrow_mut();
+        let label_changed = label_cache.as_ref().is_none_or(
+            |cache| cache.label != self.label() || cache.font_size != self.label_style.font_size
+        );
+        if label_changed {
+            let font = ggez::graphics::Font::default();
+            let text = ggez::graphics::Text::new((self.label(), font, self.label_style.font_size));
+            let dimensions = text.dimensions(ctx);
+            *label_cache = Some(LabelCache { label: self.label(), font_size: self.label_style.font_size, text, dimensions });
+        }
+        let label_cache = label_cache.as_mut().unwrap();
+
+        // Everything is built relative to the tile's corner and placed by get_local_param
+        let local_param = self.get_local_param(param, 0.0, 0.0);
+        // The caller's alpha (e.g. the rack's) combined with the tile's own
+        let alpha = param.color.a * self.fade.alpha;
+        let mut colour = self.colour();
+        colour.a *= alpha;
+        // Each part applies the tile's blend mode itself and restores the previous one afterwards
+        meshes.body.set_blend_mode(self.blend_mode);
+        ggez::graphics::draw(ctx, &meshes.body, local_param.color(colour))?;
+
+        if self.high_contrast {
+            meshes.outline.set_blend_mode(self.blend_mode);
+            ggez::graphics::draw(ctx, &meshes.outline, local_param.color(Color { a: alpha, ..Color::BLACK }))?;
+        }
+
+        if self.focused {
+            meshes.focus_ring.set_blend_mode(self.blend_mode);
+            let ring_colour = Color { a: FOCUS_RING_COLOUR.a * alpha, ..FOCUS_RING_COLOUR };
+            ggez::graphics::draw(ctx, &meshes.focus_ring, local_param.color(ring_colour))?;
+        }
+
+        label_cache.text.set_blend_mode(self.blend_mode);
+        let text_param = self.get_local_param(
+            param,
+            (self.config.tile_width / 2.0) - (label_cache.dimensions.w / 2.0),
+            (self.config.tile_height / 2.0) - (label_cache.dimensions.h / 2.0),
+        );
+        ggez::graphics::draw(ctx, &label_cache.text, text_param.color(Color { a: alpha, ..self.label_colour() }))?;
+        Ok(())
+    }
+
+    fn dimensions(&self, ctx: &mut ggez::Context) -> Option<ggez::graphics::Rect> {
+        Some(
+            ggez::graphics::Rect::new(
+                self.x,
+                self.y,
+                self.config.tile_width,
+                self.config.tile_height,
+            )
+        )
+    }
+
+    fn set_blend_mode(&mut self, mode: Option<ggez::graphics::BlendMode>) {
+        self.blend_mode = mode;
+    }
+
+    fn blend_mode(&self) -> Option<ggez::graphics::BlendMode> {
+        self.blend_mode
+    }
+}
+
+/// A drag in progress: the tile being dragged, where on it the pointer grabbed it, and where the
+/// pointer last put it
+struct Drag {
+    index: usize,
+    offset_x: f32,
+    offset_y: f32,
+    target_x: f32,
+    target_y: f32,
+    // Whether dropping onto another tile swaps the two rather than inserting
+    can_swap: bool,
+}
+
+/// Owns the active drag, if any. Only one tile can be dragged at a time.
+///
+/// Pointer motion only updates the drag's target; the dragged tile catches up to it once per
+/// update, so it moves smoothly even when motion events arrive in bursts.
+struct DragController {
+    active: Option<Drag>,
+    smoothing: f32,
+}
+
+impl DragController {
+    fn new() -> DragController {
+        DragController {
+            active: None,
+            smoothing: DEFAULT_DRAG_SMOOTHING,
+        }
+    }
+
+    /// Starts dragging the tile at `index`, currently at `tile_x`, `tile_y`, with the pointer at
+    /// `x`, `y`
+    fn start(&mut self, index: usize, tile_x: f32, tile_y: f32, x: f32, y: f32, can_swap: bool) {
+        self.active = Some(Drag {
+            index,
+            offset_x: x - tile_x,
+            offset_y: y - tile_y,
+            target_x: tile_x,
+            target_y: tile_y,
+            can_swap,
+        });
+    }
+
+    fn dragging_index(&self) -> Option<usize> {
+        self.active.as_ref().map(|drag| drag.index)
+    }
+
+    fn can_swap(&self) -> bool {
+        self.active.as_ref().is_some_and(|drag| drag.can_swap)
+    }
+
+    /// Records that the pointer has moved to `x`, `y`
+    fn move_to(&mut self, x: f32, y: f32) {
+        if let Some(drag) = &mut self.active {
+            drag.target_x = x - drag.offset_x;
+            drag.target_y = y - drag.offset_y;
+        }
+    }
+
+    /// Where the dragged tile's top-left corner should end up, i.e. under the pointer
+    fn target(&self) -> Option<(f32, f32)> {
+        self.active.as_ref().map(|drag| (drag.target_x, drag.target_y))
+    }
+
+    /// Moves the dragged tile closer to the pointer, as far as it gets in `dt` seconds
+    fn step(&self, tile: &mut Tile, dt: f32) {
+        if let Some((target_x, target_y)) = self.target() {
+            let follow = 1.0 - self.smoothing.powf(dt / DRAG_SMOOTHING_PERIOD.as_secs_f32());
+            tile.set_pos(tile.x + (target_x - tile.x) * follow, tile.y + (target_y - tile.y) * follow);
+        }
+    }
+
+    /// Ends the drag, returning the index of the tile that was being dragged
+    fn finish(&mut self) -> Option<usize> {
+        self.active.take().map(|drag| drag.index)
+    }
+}
+
+/// A row of tiles that can be rearranged by dragging them around or with the keyboard.
+/// Coordinates passed to and returned by its methods are in the rack's own coordinate space.
+pub struct TileRack {
+    x: f32,
+    y: f32,
+    tiles: Vec<Tile>,
+    size: usize,
+    // Id for the next tile added to the rack, so every tile that's ever been on it has its own
+    next_id: usize,
+    config: TileRackConfig,
+    drag: DragController,
+    jitter: bool,
+    fade: Fade,
+    blend_mode: Option<ggez::graphics::BlendMode>,
+    // Multiplier for how fast tiles slide and fade; infinite makes them instant
+    animation_speed: f32,
+    // Curve followed by tiles sliding into their slots
+    easing: Easing,
+    label_style: LabelStyle,
+    high_contrast: bool,
+    // Whether dropping a tile right on top of another swaps them, rather than always inserting
+    swap_on_drop: bool,
+    // Most tiles the rack can hold, if there's a limit
+    capacity: Option<usize>,
+}
+
+impl TileRack {
+    /// Creates a rack with its top-left corner at `x`, `y` and a tile for each letter, sized and
+    /// coloured according to `config`
+    pub fn new(x: f32, y: f32, letters: &str, config: TileRackConfig) -> TileRack {
+        // Counted in chars rather than bytes, so letters outside ASCII still get one tile each
+        let tiles: Vec<Tile> = letters.chars().enumerate().map(
+            |(index, letter)| {
+                let tile_x = x + (index as f32) * (config.tile_width + config.tile_spacing);
+                let tile_y = y;
+                Tile::new(index, tile_x, tile_y, letter, config)
+            }
+        ).collect();
+        let size = tiles.len();
+
+        TileRack {
+            x,
+            y,
+            tiles,
+            size,
+            next_id: size,
+            config,
+            drag: DragController::new(),
+            jitter: false,
+            fade: Fade::new(1.0),
+            blend_mode: None,
+            animation_speed: 1.0,
+            easing: Easing::Linear,
+            label_style: LabelStyle::default(),
+            high_contrast: false,
+            swap_on_drop: true,
+            capacity: None,
+        }
+    }
+
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn y(&self) -> f32 {
+        self.y
+    }
+
+    /// The tiles in slot order
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    /// Number of slots on the rack
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn config(&self) -> &TileRackConfig {
+        &self.config
+    }
+
+    pub fn label_style(&self) -> LabelStyle {
+        self.label_style
+    }
+
+    pub fn high_contrast(&self) -> bool {
+        self.high_contrast
+    }
+
+    /// Locks or unlocks the tile at `index`
+    pub fn set_locked(&mut self, index: usize, locked: bool) {
+        self.tiles[index].locked = locked;
+    }
+
+    /// Sets whether tiles picked up with `handle_mouse_down` swap places with a tile they're
+    /// dropped right on top of, rather than always being inserted between tiles
+    pub fn set_swap_on_drop(&mut self, enabled: bool) {
+        self.swap_on_drop = enabled;
+    }
+
+    /// Limits how many tiles the rack can hold, or lifts the limit with `None`. Tiles already on
+    /// the rack stay, but no more can be added while it's at or over the limit.
+    pub fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+    }
+
+    /// Whether the rack has as many tiles as its capacity allows
+    pub fn is_full(&self) -> bool {
+        self.capacity.is_some_and(|capacity| self.size >= capacity)
+    }
+
+    /// Fades the whole rack out; `is_fading` says when it's done
+    pub fn fade_out(&mut self) {
+        self.fade.fade_out();
+    }
+
+    /// Fades the whole rack back in, e.g. after `fade_out` or `set_alpha(0.0)`
+    pub fn fade_in(&mut self) {
+        self.fade.fade_in();
+    }
+
+    /// Sets the opacity of the whole rack straight away, from 0 for hidden to 1, stopping any
+    /// fade in progress. Fading in from 0 makes the rack appear gradually.
+    pub fn set_alpha(&mut self, alpha: f32) {
+        self.fade = Fade::new(alpha.clamp(0.0, 1.0));
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.fade.is_fading()
+    }
+
+    /// Moves every tile to height `y` above its slot and hides it, so that it fades in as it
+    /// falls into place
+    pub fn drop_in(&mut self, y: f32) {
+        for index in 0..self.size {
+            let tile_x = self.get_slot_x(index);
+            let tile = &mut self.tiles[index];
+            tile.set_pos(tile_x, y);
+            tile.fade = Fade::new(0.0);
+            tile.fade.fade_in();
+        }
+    }
+
+    /// Draws tiles from `bag` onto the end of the rack until there are `size` of them, the rack is
+    /// full or the bag runs out. The new tiles fade in as they fall into their slots.
+    pub fn refill(&mut self, bag: &mut TileBag, size: usize) {
+        let size = self.capacity.map_or(size, |capacity| size.min(capacity));
+        for letter in bag.draw(size.saturating_sub(self.size)) {
+            let tile = self.deal_tile(self.size, letter);
+            self.tiles.push(tile);
+            self.size += 1;
+        }
+        self.update_jitter();
+    }
+
+    /// Marks the tile at `index` to go back in the bag at the next `exchange`, or unmarks it.
+    /// Marked tiles stick up out of the rack until then.
+    pub fn set_marked_for_exchange(&mut self, index: usize, marked: bool) {
+        self.tiles[index].marked_for_exchange = marked;
+    }
+
+    /// Sets the letter the blank at `index` stands for, or clears it with None. Does nothing if
+    /// the tile isn't a blank.
+    pub fn designate_blank(&mut self, index: usize, letter: Option<char>) {
+        let tile = &mut self.tiles[index];
+        if tile.is_blank() {
+            tile.designation = letter.map(|letter| letter.to_ascii_uppercase());
+        }
+    }
+
+    /// Swaps the tiles marked for exchange for new ones from `bag`, which fade in as they drop
+    /// into the same slots. If the bag runs short, the tiles it can't replace stay marked.
+    /// Returns how many tiles were exchanged.
+    pub fn exchange(&mut self, bag: &mut TileBag) -> usize {
+        self.drag.finish();
+        let marked: Vec<usize> = (0..self.size).filter(|index| self.tiles[*index].marked_for_exchange).collect();
+        // Draw before putting anything back so the same tiles can't come straight out again
+        let replacements = bag.draw(marked.len());
+        for (index, letter) in marked.iter().zip(replacements.iter()) {
+            let tile = self.deal_tile(*index, *letter);
+            let old_tile = std::mem::replace(&mut self.tiles[*index], tile);
+            bag.put_back(old_tile.letter);
+        }
+        self.update_jitter();
+        replacements.len()
+    }
+
+    /// Makes a new tile for the slot at `index`, hidden above it so that it fades in as it falls
+    /// into place
+    fn deal_tile(&mut self, index: usize, letter: char) -> Tile {
+        let mut tile = Tile::new(self.next_id, self.get_slot_x(index), self.y - self.config.tile_height, letter, self.config);
+        self.next_id += 1;
+        tile.label_style = self.label_style;
+        tile.high_contrast = self.high_contrast;
+        tile.blend_mode = self.blend_mode;
+        tile.fade = Fade::new(0.0);
+        tile.fade.fade_in();
+        tile
+    }
+
+    /// Width of the rack from the left edge of the first slot to the right edge of the last
+    pub fn get_width(&self) -> f32 {
+        (self.config.tile_width + self.config.tile_spacing) * self.size as f32 - self.config.tile_spacing
+    }
+
+    /// Moves the whole rack, tiles included, so its top-left corner is at `x`, `y`
+    pub fn move_to(&mut self, x: f32, y: f32) {
+        let (dx, dy) = (x - self.x, y - self.y);
+        self.x = x;
+        self.y = y;
+        for tile in self.tiles.iter_mut() {
+            tile.set_pos(tile.x + dx, tile.y + dy);
+        }
+    }
+
+    /// x coordinate of the slot at `index`
+    pub fn get_slot_x(&self, index: usize) -> f32 {
+        self.x + (index as f32) * (self.config.tile_width + self.config.tile_spacing)
+    }
+
+    /// Tile ids in their current order, for putting the rack back later with `restore_order`
+    pub fn get_order(&self) -> Vec<usize> {
+        self.tiles.iter().map(|tile| tile.id).collect()
+    }
+
+    /// Rearranges the tiles into an order saved with `get_order`. Tiles animate to their slots.
+    pub fn restore_order(&mut self, order: &[usize]) {
+        self.tiles.sort_by_key(
+            |tile| order.iter().position(|id| *id == tile.id)
+        );
+        self.update_jitter();
+    }
+
+    /// Sorts the tiles using `arrangement`. Locked tiles keep their slots and the rest are
+    /// arranged around them; tiles animate to their new slots.
+    pub fn arrange(&mut self, arrangement: Arrangement) {
+        self.arrange_with(|tiles| arrangement.order(tiles));
+    }
+
+    /// Sorts the tiles into a custom order, like `arrange`. `order` is given the unlocked tiles,
+    /// in their current order, and returns their ids in the order they should go. Ids that
+    /// aren't among those tiles, or come up more than once, are ignored, and any tiles it leaves
+    /// out go after the rest in their current order.
+    pub fn arrange_with(&mut self, order: impl Fn(&[&Tile]) -> Vec<usize>) {
+        let unlocked: Vec<&Tile> = self.tiles.iter().filter(|tile| !tile.locked).collect();
+        let mut arranged: Vec<usize> = order(&unlocked).into_iter()
+            .filter(|id| unlocked.iter().any(|tile| tile.id == *id))
+            .unique()
+            .collect();
+        let missing: Vec<usize> = unlocked.iter()
+            .map(|tile| tile.id)
+            .filter(|id| !arranged.contains(id))
+            .collect();
+        arranged.extend(missing);
+        let mut arranged = arranged.into_iter();
+        let order: Vec<usize> = self.tiles.iter().map(
+            |tile| if tile.locked {
+                tile.id
+            } else {
+                arranged.next().unwrap()
+            }
+        ).collect();
+        self.restore_order(&order);
+    }
+
+    /// Changes how the letters on all the tiles are written
+    pub fn set_label_style(&mut self, label_style: LabelStyle) {
+        self.label_style = LabelStyle {
+            font_size: label_style.font_size.clamp(1.0, MAX_LABEL_SIZE),
+            ..label_style
+        };
+        for tile in self.tiles.iter_mut() {
+            tile.label_style = self.label_style;
+        }
+    }
+
+    /// Draws the rack, with every tile in its slot, as a standalone SVG image
+    pub fn to_svg(&self) -> String {
+        svg_document(self.get_width(), self.config.tile_height, &self.svg_elements(self.x, self.y))
+    }
+
+    /// SVG elements for every tile in its slot, positioned relative to `origin_x`, `origin_y`
+    fn svg_elements(&self, origin_x: f32, origin_y: f32) -> String {
+        self.tiles.iter().enumerate().map(
+            |(index, tile)| tile.svg_elements(self.get_slot_x(index) - origin_x, self.y - origin_y)
+        ).collect()
+    }
+
+    /// Turns the stronger colours and outlines of large-print mode on or off
+    pub fn set_high_contrast(&mut self, enabled: bool) {
+        self.high_contrast = enabled;
+        for tile in self.tiles.iter_mut() {
+            tile.high_contrast = enabled;
+        }
+    }
+
+    /// Turns the hand-placed look on or off
+    pub fn set_jitter(&mut self, enabled: bool) {
+        self.jitter = enabled;
+        self.update_jitter();
+    }
+
+    /// Gives every tile the jitter for its current slot. Needs calling whenever tiles change slot.
+    fn update_jitter(&mut self) {
+        for (slot, tile) in self.tiles.iter_mut().enumerate() {
+            tile.set_jitter(slot, self.jitter);
+        }
+    }
+
+    /// Puts the tiles in the order given by `letters`, e.g. as saved in a crash dump. Returns
+    /// false and leaves the rack alone if the letters don't match the tiles on the rack.
+    pub fn restore_letters(&mut self, letters: &str) -> bool {
+        let mut remaining: Vec<&Tile> = self.tiles.iter().collect();
+        let mut order = Vec::with_capacity(self.size);
+        for letter in letters.chars() {
+            match remaining.iter().position(|tile| tile.letter == letter) {
+                Some(index) => order.push(remaining.remove(index).id),
+                None => return false,
+            }
+        }
+        if !remaining.is_empty() {
+            return false;
+        }
+        self.restore_order(&order);
+        true
+    }
+
+    /// Replaces all the tiles with new ones for `letters`, in order, which fade in as they fall
+    /// into their slots. Unlike `restore_letters`, the letters don't have to match the tiles
+    /// already on the rack. Any drag in progress is let go first.
+    pub fn set_letters(&mut self, letters: &str) {
+        self.drag.finish();
+        self.tiles.clear();
+        self.size = 0;
+        for letter in letters.chars() {
+            let tile = self.deal_tile(self.size, letter);
+            self.tiles.push(tile);
+            self.size += 1;
+        }
+        self.update_jitter();
+    }
+
+    /// Picks up the tile at `index`, grabbed by the pointer at `x`, `y`. Locked tiles stay put.
+    /// If `can_swap` is set, dropping the tile right on top of another swaps the two; otherwise
+    /// it's always inserted between tiles.
+    pub fn start_drag(&mut self, index: usize, x: f32, y: f32, can_swap: bool) {
+        let tile = &self.tiles[index];
+        if !tile.locked {
+            self.drag.start(index, tile.x, tile.y, x, y, can_swap);
+        }
+    }
+
+    /// Index of the tile that the dragged tile would swap with if it were dropped now, if any
+    fn get_swap_target(&self) -> Option<usize> {
+        let dragging_index = self.drag.dragging_index()?;
+        let (target_x, target_y) = self.drag.target()?;
+        if !self.drag.can_swap() {
+            return None;
+        }
+        // Compare against where tiles rest rather than where they're drawn, since the drag
+        // preview moves the other tiles around
+        (0..self.size).find(
+            |index| {
+                *index != dragging_index
+                    && !self.tiles[*index].locked
+                    && (target_x - self.get_slot_x(*index)).hypot(target_y - self.y) <= SWAP_RADIUS
+            }
+        )
+    }
+
+    /// Moves the dragged tile, if there is one, to follow the pointer
+    pub fn drag_to(&mut self, x: f32, y: f32) {
+        self.drag.move_to(x, y);
+    }
+
+    /// Drops the dragged tile, either swapping it with the tile it was dropped on or inserting it
+    /// into the slot nearest to where it was let go
+    pub fn end_drag(&mut self) {
+        // Use where the pointer left the tile rather than where it's drawn, which may be lagging
+        let target = self.drag.target();
+        let swap_target = self.get_swap_target();
+        if let (Some(index), Some((target_x, target_y))) = (self.drag.finish(), target) {
+            self.tiles[index].set_pos(target_x, target_y);
+            if let Some(swap_index) = swap_target {
+                self.tiles.swap(index, swap_index);
+            } else {
+                let new_index = self.get_new_tile_index(target_x);
+                let tile = self.tiles.remove(index);
+                self.tiles.insert(new_index, tile);
+            }
+            self.update_jitter();
+        }
+    }
+
+    /// Takes the tile being dragged off the rack, e.g. to put it on a board. The tiles after it
+    /// slide along to close the gap.
+    fn take_dragged_tile(&mut self) -> Option<Tile> {
+        let index = self.drag.finish()?;
+        Some(self.remove_tile(index))
+    }
+
+    /// Takes the tile at `index` off the rack, e.g. to put it back in the bag. The tiles after it
+    /// slide along to close the gap. Any drag in progress is let go first.
+    pub fn remove_tile(&mut self, index: usize) -> Tile {
+        self.drag.finish();
+        let mut tile = self.tiles.remove(index);
+        self.size -= 1;
+        tile.focused = false;
+        tile.marked_for_exchange = false;
+        tile.set_jitter(0, false);
+        self.update_jitter();
+        tile
+    }
+
+    /// Adds `tile` to the end of the rack, already being dragged by the pointer at `x`, `y`, e.g.
+    /// when it's picked up off a board. The rest of the rack makes room for it wherever it's
+    /// dropped, as if it had been dragged from the last slot.
+    fn insert_dragged_tile(&mut self, tile: Tile, x: f32, y: f32) {
+        let (tile_x, tile_y) = (tile.x, tile.y);
+        self.tiles.push(tile);
+        self.size += 1;
+        self.update_jitter();
+        // Dropping it on another tile should put it next to that tile, not swap it onto the board
+        self.drag.start(self.size - 1, tile_x, tile_y, x, y, false);
+    }
+
+    /// Index of the tile being dragged, if any
+    pub fn dragging_index(&self) -> Option<usize> {
+        self.drag.dragging_index()
+    }
+
+    /// Focuses and picks up the tile under the pointer, if there is one, and returns its index
+    pub fn handle_mouse_down(&mut self, x: f32, y: f32) -> Option<usize> {
+        let index = self.get_tile_index_at(x, y)?;
+        self.set_focus(Some(index));
+        self.start_drag(index, x, y, self.swap_on_drop);
+        Some(index)
+    }
+
+    /// Moves the dragged tile, if any, along with the pointer
+    pub fn handle_mouse_motion(&mut self, x: f32, y: f32) {
+        self.drag_to(x, y);
+    }
+
+    /// Drops the dragged tile, if any, where the pointer was let go
+    pub fn handle_mouse_up(&mut self, x: f32, y: f32) {
+        self.drag_to(x, y);
+        self.end_drag();
+    }
+
+    /// Lets go of the dragged tile without moving it to a new slot, so it slides back to its slot
+    pub fn cancel_drag(&mut self) {
+        self.drag.finish();
+    }
+
+    /// Whether anything in the rack is moving or fading, i.e. whether it needs redrawing
+    pub fn is_animating(&self) -> bool {
+        self.drag.dragging_index().is_some()
+            || self.fade.is_fading()
+            || self.tiles.iter().any(
+                |tile| tile.fade.is_fading() || tile.animation.is_some()
+            )
+    }
+
+    /// Sets how much the dragged tile lags behind the pointer to smooth out its motion, from 0
+    /// (no smoothing) up to but not including 1
+    pub fn set_drag_smoothing(&mut self, smoothing: f32) {
+        self.drag.smoothing = smoothing.clamp(0.0, 0.99);
+    }
+
+    /// Sets how fast tiles slide and fade, from MIN_ANIMATION_SPEED to MAX_ANIMATION_SPEED, or
+    /// `None` to make them instant
+    pub fn set_animation_speed(&mut self, speed: Option<f32>) {
+        self.animation_speed = match speed {
+            Some(speed) => speed.clamp(MIN_ANIMATION_SPEED, MAX_ANIMATION_SPEED),
+            None => f32::INFINITY,
+        };
+    }
+
+    /// Sets the curve tiles follow when sliding into their slots, e.g. after a drop or when the
+    /// rack is rearranged
+    pub fn set_easing(&mut self, easing: Easing) {
+        self.easing = easing;
+    }
+
+    /// Index of the tile with keyboard focus, if any
+    pub fn get_focused_tile_index(&self) -> Option<usize> {
+        self.tiles.iter().position(|tile| tile.focused)
+    }
+
+    /// Moves keyboard focus to the tile at `index`, or clears it if `None`
+    pub fn set_focus(&mut self, index: Option<usize>) {
+        for (tile_index, tile) in self.tiles.iter_mut().enumerate() {
+            tile.focused = Some(tile_index) == index;
+        }
+    }
+
+    /// Moves focus to the next (or previous) tile, wrapping around at the ends of the rack
+    pub fn cycle_focus(&mut self, forward: bool) {
+        if self.size == 0 {
+            return;
+        }
+        let new_index = match self.get_focused_tile_index() {
+            Some(index) if forward => (index + 1) % self.size,
+            Some(index) => (index + self.size - 1) % self.size,
+            None if forward => 0,
+            None => self.size - 1,
+        };
+        self.set_focus(Some(new_index));
+    }
+
+    /// Moves the focused tile to `new_index`, shifting the tiles in between along. The tiles
+    /// animate to their new slots. Does nothing to locked tiles or while a tile is being dragged.
+    pub fn move_focused_tile(&mut self, new_index: usize) {
+        let index = match self.get_focused_tile_index() {
+            Some(index) if self.drag.dragging_index().is_none() && !self.tiles[index].locked => index,
+            _ => return,
+        };
+        let tile = self.tiles.remove(index);
+        self.tiles.insert(new_index.min(self.size - 1), tile);
+        self.update_jitter();
+    }
+
+    /// Tiles with their indices, in the order they should be drawn. Sorting by whether each tile
+    /// is being dragged makes sure the tile being dragged gets drawn last (i.e. on top).
+    fn tiles_in_draw_order(&self) -> impl DoubleEndedIterator<Item = (usize, &Tile)> {
+        let dragging_index = self.drag.dragging_index();
+        self.tiles.iter().enumerate().sorted_by_key(move |(index, _)| Some(*index) == dragging_index)
+    }
+
+    /// Returns the index of the tile under the given point, if any. This checks where each tile
+    /// actually is right now rather than its slot, so tiles that are mid-animation or pushed
+    /// aside by a drag are hit where they're drawn.
+    pub fn get_tile_index_at(&self, x: f32, y: f32) -> Option<usize> {
+        let click_point = Point2{x, y};
+        // Check tiles in reverse draw order so that where tiles overlap, the one on top wins
+        for (index, tile) in self.tiles_in_draw_order().rev() {
+            let tile_bounds = ggez::graphics::Rect::new(tile.x, tile.y, tile.config.tile_width, tile.config.tile_height);
+            if tile_bounds.contains(click_point) {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Location of the dragged tile's centre if it were dropped now
+    fn get_drop_point(&self) -> Option<(f32, f32)> {
+        self.drag.target().map(|(x, y)| (x + self.config.tile_width / 2.0, y + self.config.tile_height / 2.0))
+    }
+
+    /// Slot that a tile with its left edge at `x` would be dropped into
+    pub fn get_new_tile_index(&self, x: f32) -> usize {
+        let tile_position = (x - self.x + (self.config.tile_width / 2.0)) / (self.config.tile_width + self.config.tile_spacing);
+        if tile_position < 0.0 {
+            0
+        } else if tile_position > (self.size - 1) as f32 {
+            self.size - 1
+        } else {
+            tile_position as usize
+        }
+    }
+
+    /// Advances fades, drags and animations by the time since the last frame. Call it once per
+    /// frame.
+    pub fn update(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        self.advance(ggez::timer::delta(ctx));
+        Ok(())
+    }
+
+    /// Advances fades, drags and animations by `dt`, for when the caller wants to control time
+    /// itself, e.g. to pause or slow things down
+    pub fn advance(&mut self, dt: Duration) {
+        let dt = dt.as_secs_f32();
+        // Animations run at their own speed, but the dragged tile always keeps up with the pointer
+        let animation_dt = scale_time(dt, self.animation_speed);
+        self.fade.update(animation_dt);
+        for tile in self.tiles.iter_mut() {
+            tile.fade.update(animation_dt);
+        }
+
+        let dragging_index = self.drag.dragging_index();
+        // Where the pointer has put the dragged tile rather than where it's drawn, since that's
+        // where end_drag will drop it
+        let maybe_dragging_index_x = dragging_index.zip(self.drag.target()).map(|(index, (x, _))| (index, x));
+        let swap_target = self.get_swap_target();
+
+        let new_tile_x_positions: Vec<f32> = (0..self.size).map(
+            |index| {
+                let mut tile_x = self.get_slot_x(index);
+                if let (Some(swap_index), Some(dragging_initial_index)) = (swap_target, dragging_index) {
+                    // Only the tile being swapped with moves, into the dragged tile's old slot
+                    if index == swap_index {
+                        tile_x = self.get_slot_x(dragging_initial_index);
+                    }
+                } else if let Some((dragging_initial_index, dragging_x)) = maybe_dragging_index_x {
+                    // new_index is the index that the tile would get if it were to be dropped now
+                    let new_index = self.get_new_tile_index(dragging_x);
+                    if new_index <= index && index <= dragging_initial_index {
+                        tile_x += self.config.tile_width + self.config.tile_spacing;
+                    }
+                    else if dragging_initial_index <= index && index <= new_index {
+                        tile_x -= self.config.tile_width + self.config.tile_spacing;
+                    }
+                }
+                tile_x
+            }
+        ).collect();
+
+        for (index, (tile, new_x)) in self.tiles.iter_mut().zip(new_tile_x_positions).enumerate() {
+            if Some(index) == dragging_index {
+                self.drag.step(tile, dt);
+            } else {
+                let new_y = if tile.marked_for_exchange { self.y - EXCHANGE_LIFT } else { self.y };
+                tile.animate_towards(new_x, new_y, animation_dt, self.easing);
+            }
+        }
+    }
+}
+
+
+/// Ways of automatically sorting the tiles on a rack
+#[derive(Clone, Copy)]
+pub enum Arrangement {
+    Alphabetical,
+    /// Highest-scoring letters first
+    Value,
+    /// Common prefixes at the start and suffixes at the end, with everything else in between, to
+    /// help spot words
+    Chunks,
+}
+
+impl Arrangement {
+    pub const ALL: [Arrangement; 3] = [Arrangement::Alphabetical, Arrangement::Value, Arrangement::Chunks];
+
+    /// Short description for menus
+    pub fn name(&self) -> &'static str {
+        match self {
+            Arrangement::Alphabetical => "Sort A-Z",
+            Arrangement::Value => "Sort by value",
+            Arrangement::Chunks => "Group word chunks",
+        }
+    }
+
+    /// Returns the ids of `tiles` in their arranged order
+    fn order(&self, tiles: &[&Tile]) -> Vec<usize> {
+        let mut sorted: Vec<&Tile> = tiles.iter().copied()
+            .sorted_by_key(|tile| tile.letter.to_ascii_uppercase())
+            .collect();
+        match self {
+            Arrangement::Alphabetical => (),
+            Arrangement::Value => sorted.sort_by_key(|tile| std::cmp::Reverse(letter_value(tile.letter))),
+            Arrangement::Chunks => {
+                let prefixes: Vec<&Tile> = PREFIX_CHUNKS.iter()
+                    .flat_map(|chunk| take_chunk(&mut sorted, chunk))
+                    .collect();
+                // Suffixes go at the end in the order they're listed, so a plural S comes last
+                let suffixes: Vec<Vec<&Tile>> = SUFFIX_CHUNKS.iter()
+                    .map(|chunk| take_chunk(&mut sorted, chunk))
+                    .collect();
+                sorted = prefixes.into_iter()
+                    .chain(sorted)
+                    .chain(suffixes.into_iter().flatten())
+                    .collect();
+            },
+        }
+        sorted.iter().map(|tile| tile.id).collect()
+    }
+}
+
+/// Removes the tiles spelling out `chunk` from `tiles` and returns them in order, or returns
+/// nothing and leaves `tiles` alone if not every letter is there
+fn take_chunk<'a>(tiles: &mut Vec<&'a Tile>, chunk: &str) -> Vec<&'a Tile> {
+    let mut remaining = tiles.clone();
+    let mut taken = Vec::new();
+    for letter in chunk.chars() {
+        match remaining.iter().position(|tile| tile.letter.to_ascii_uppercase() == letter) {
+            Some(index) => taken.push(remaining.remove(index)),
+            None => return Vec::new(),
+        }
+    }
+    *tiles = remaining;
+    taken
+}
+
+/// Standard English tile value of a letter; anything else (e.g. a blank) is worth nothing
+fn letter_value(letter: char) -> u32 {
+    match letter.to_ascii_uppercase() {
+        'A' | 'E' | 'I' | 'L' | 'N' | 'O' | 'R' | 'S' | 'T' | 'U' => 1,
+        'D' | 'G' => 2,
+        'B' | 'C' | 'M' | 'P' => 3,
+        'F' | 'H' | 'V' | 'W' | 'Y' => 4,
+        'K' => 5,
+        'J' | 'X' => 8,
+        'Q' | 'Z' => 10,
+        _ => 0,
+    }
+}
+
+impl ggez::graphics::Drawable for TileRack {
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        param: ggez::graphics::DrawParam,
+    ) -> ggez::GameResult {
+        // Tile positions are already in the rack's coordinate space, so the rack's param applies
+        // to them unchanged apart from fading them along with the rack
+        let tile_param = param.color(Color { a: param.color.a * self.fade.alpha, ..param.color });
+        for (_, tile) in self.tiles_in_draw_order() {
+            ggez::graphics::draw(ctx, tile, tile_param)?;
+        }
+        Ok(())
+    }
+
+    fn dimensions(&self, ctx: &mut ggez::Context) -> Option<ggez::graphics::Rect> {
+        Some(
+            ggez::graphics::Rect::new(
+                self.x,
+                self.y,
+                (self.config.tile_width + self.config.tile_spacing) * self.size as f32,
+                self.config.tile_height,
+            )
+        )
+    }
+
+    /// Sets the blend mode for the whole rack. It's passed on to every tile, since tiles are drawn
+    /// individually.
+    fn set_blend_mode(&mut self, mode: Option<ggez::graphics::BlendMode>) {
+        self.blend_mode = mode;
+        for tile in self.tiles.iter_mut() {
+            tile.set_blend_mode(mode);
+        }
+    }
+
+    fn blend_mode(&self) -> Option<ggez::graphics::BlendMode> {
+        self.blend_mode
+    }
+}
+
+/// What pressing the pointer on a `Board` did
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoardPress {
+    /// There was no tile there
+    Missed,
+    /// The tile is now on the rack, being dragged
+    PickedUp,
+    /// There was a tile there, but the rack was too full to take it
+    RackFull,
+}
+
+/// A grid of cells that tiles can be dragged onto from a `TileRack` and back again. It's drawn
+/// in the same coordinate space as the rack, so give both the same `DrawParam`.
+pub struct Board {
+    x: f32,
+    y: f32,
+    columns: usize,
+    rows: usize,
+    // Row by row
+    cells: Vec<Option<Tile>>,
+    // Cells are the size of this config's tiles
+    config: TileRackConfig,
+    blend_mode: Option<ggez::graphics::BlendMode>,
+    animation_speed: f32,
+}
+
+impl Board {
+    /// Creates an empty board with its top-left corner at `x`, `y`, with cells sized to fit the
+    /// tiles of `config`. Usually that's the config of the rack the tiles come from.
+    pub fn new(x: f32, y: f32, columns: usize, rows: usize, config: TileRackConfig) -> Board {
+        Board {
+            x,
+            y,
+            columns,
+            rows,
+            cells: (0..columns * rows).map(|_| None).collect(),
+            config,
+            blend_mode: None,
+            animation_speed: 1.0,
+        }
+    }
+
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn y(&self) -> f32 {
+        self.y
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Moves the whole board, tiles included, so its top-left corner is at `x`, `y`
+    pub fn move_to(&mut self, x: f32, y: f32) {
+        let (dx, dy) = (x - self.x, y - self.y);
+        self.x = x;
+        self.y = y;
+        for tile in self.cells.iter_mut().flatten() {
+            tile.set_pos(tile.x + dx, tile.y + dy);
+        }
+    }
+
+    pub fn get_width(&self) -> f32 {
+        (self.config.tile_width + BOARD_CELL_SPACING) * self.columns as f32 - BOARD_CELL_SPACING
+    }
+
+    pub fn get_height(&self) -> f32 {
+        (self.config.tile_height + BOARD_CELL_SPACING) * self.rows as f32 - BOARD_CELL_SPACING
+    }
+
+    /// Top-left corner of the cell at `column`, `row`
+    pub fn get_cell_pos(&self, column: usize, row: usize) -> (f32, f32) {
+        (
+            self.x + column as f32 * (self.config.tile_width + BOARD_CELL_SPACING),
+            self.y + row as f32 * (self.config.tile_height + BOARD_CELL_SPACING),
+        )
+    }
+
+    /// The tile in the cell at `column`, `row`, if there is one
+    pub fn get_tile(&self, column: usize, row: usize) -> Option<&Tile> {
+        self.cells[row * self.columns + column].as_ref()
+    }
+
+    /// The empty cell whose centre is closest to `x`, `y`, as long as that point is on the board
+    pub fn get_nearest_empty_cell(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        let on_board = self.x <= x && x < self.x + self.get_width()
+            && self.y <= y && y < self.y + self.get_height();
+        if !on_board {
+            return None;
+        }
+        let distance_to = |(column, row): (usize, usize)| {
+            let (cell_x, cell_y) = self.get_cell_pos(column, row);
+            (cell_x + self.config.tile_width / 2.0 - x).hypot(cell_y + self.config.tile_height / 2.0 - y)
+        };
+        (0..self.rows)
+            .cartesian_product(0..self.columns)
+            .map(|(row, column)| (column, row))
+            .filter(|(column, row)| self.get_tile(*column, *row).is_none())
+            .min_by(|a, b| distance_to(*a).total_cmp(&distance_to(*b)))
+    }
+
+    /// Sets how fast tiles slide into their cells; see `TileRack::set_animation_speed`
+    pub fn set_animation_speed(&mut self, speed: Option<f32>) {
+        self.animation_speed = match speed {
+            Some(speed) => speed.clamp(MIN_ANIMATION_SPEED, MAX_ANIMATION_SPEED),
+            None => f32::INFINITY,
+        };
+    }
+
+    /// Picks up the tile under the pointer at `x`, `y`, if there is one, moving it onto `rack`
+    /// as the tile being dragged. If `rack` is full the tile stays where it is, and it's up to the
+    /// caller what to do about it, e.g. tell the player or pick it up into another rack instead.
+    pub fn handle_mouse_down(&mut self, rack: &mut TileRack, x: f32, y: f32) -> BoardPress {
+        let index = self.cells.iter().position(
+            |cell| match cell {
+                Some(tile) => tile.x <= x && x < tile.x + tile.config.tile_width
+                    && tile.y <= y && y < tile.y + tile.config.tile_height,
+                None => false,
+            }
+        );
+        match index {
+            Some(_) if rack.is_full() => BoardPress::RackFull,
+            Some(index) => {
+                let tile = self.cells[index].take().unwrap();
+                rack.insert_dragged_tile(tile, x, y);
+                BoardPress::PickedUp
+            },
+            None => BoardPress::Missed,
+        }
+    }
+
+    /// Finishes a drag from `rack` with the pointer let go at `x`, `y`. If the tile was dropped
+    /// on the board, it's moved off the rack and snapped to the nearest empty cell and this
+    /// returns true. Otherwise the rack is left alone to handle the drop, which sends the tile
+    /// back to the rack.
+    pub fn handle_mouse_up(&mut self, rack: &mut TileRack, x: f32, y: f32) -> bool {
+        rack.drag_to(x, y);
+        let (column, row) = match rack.get_drop_point()
+            .and_then(|(drop_x, drop_y)| self.get_nearest_empty_cell(drop_x, drop_y)) {
+            Some(cell) => cell,
+            None => return false,
+        };
+        match rack.take_dragged_tile() {
+            Some(tile) => {
+                // The tile slides from where it was let go into the cell
+                self.cells[row * self.columns + column] = Some(tile);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Draws the board, with every tile in its cell, as a standalone SVG image. To include a rack,
+    /// use `rack_and_board_to_svg`.
+    pub fn to_svg(&self) -> String {
+        svg_document(self.get_width(), self.get_height(), &self.svg_elements(self.x, self.y))
+    }
+
+    /// SVG elements for the cells and the tiles in them, positioned relative to `origin_x`,
+    /// `origin_y`
+    fn svg_elements(&self, origin_x: f32, origin_y: f32) -> String {
+        let mut elements = String::new();
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let (cell_x, cell_y) = self.get_cell_pos(column, row);
+                let (x, y) = (cell_x - origin_x, cell_y - origin_y);
+                elements += &format!(
+                    "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                    x, y, self.config.tile_width, self.config.tile_height, svg_colour(BOARD_CELL_COLOUR),
+                );
+                if let Some(tile) = self.get_tile(column, row) {
+                    elements += &tile.svg_elements(x, y);
+                }
+            }
+        }
+        elements
+    }
+
+    /// Whether any tiles are still sliding into their cells or fading, i.e. whether the board
+    /// needs redrawing
+    pub fn is_animating(&self) -> bool {
+        self.cells.iter().flatten().any(|tile| tile.fade.is_fading() || tile.animation.is_some())
+    }
+
+    /// Advances tiles sliding into their cells by the time since the last frame. Call it once per
+    /// frame.
+    pub fn update(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        self.advance(ggez::timer::delta(ctx));
+        Ok(())
+    }
+
+    /// Advances tiles sliding into their cells by `dt`; see `TileRack::advance`
+    pub fn advance(&mut self, dt: Duration) {
+        let animation_dt = scale_time(dt.as_secs_f32(), self.animation_speed);
+        for index in 0..self.cells.len() {
+            let (cell_x, cell_y) = self.get_cell_pos(index % self.columns, index / self.columns);
+            if let Some(tile) = &mut self.cells[index] {
+                tile.fade.update(animation_dt);
+                tile.animate_towards(cell_x, cell_y, animation_dt, Easing::Linear);
+            }
+        }
+    }
+}
+
+impl ggez::graphics::Drawable for Board {
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        param: ggez::graphics::DrawParam,
+    ) -> ggez::GameResult {
+        let mut cell_colour = BOARD_CELL_COLOUR;
+        cell_colour.a *= param.color.a;
+        let mut mesh_builder = ggez::graphics::MeshBuilder::new();
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let (cell_x, cell_y) = self.get_cell_pos(column, row);
+                let rect = ggez::graphics::Rect::new(cell_x, cell_y, self.config.tile_width, self.config.tile_height);
+                mesh_builder.rectangle(ggez::graphics::DrawMode::fill(), rect, cell_colour)?;
+            }
+        }
+        let mut cells_drawable = mesh_builder.build(ctx)?;
+        cells_drawable.set_blend_mode(self.blend_mode);
+        ggez::graphics::draw(ctx, &cells_drawable, param)?;
+
+        for tile in self.cells.iter().flatten() {
+            ggez::graphics::draw(ctx, tile, param)?;
+        }
+        Ok(())
+    }
+
+    fn dimensions(&self, ctx: &mut ggez::Context) -> Option<ggez::graphics::Rect> {
+        Some(ggez::graphics::Rect::new(self.x, self.y, self.get_width(), self.get_height()))
+    }
+
+    fn set_blend_mode(&mut self, mode: Option<ggez::graphics::BlendMode>) {
+        self.blend_mode = mode;
+        for tile in self.cells.iter_mut().flatten() {
+            tile.set_blend_mode(mode);
+        }
+    }
+
+    fn blend_mode(&self) -> Option<ggez::graphics::BlendMode> {
+        self.blend_mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letters(rack: &TileRack) -> String {
+        rack.tiles().iter().map(|tile| tile.letter()).collect()
+    }
+
+    #[test]
+    fn refill_stops_at_capacity() {
+        let mut rack = TileRack::new(0.0, 0.0, "AB", TileRackConfig::default());
+        rack.set_capacity(Some(3));
+        let mut bag = TileBag::new(42);
+        rack.refill(&mut bag, 7);
+        assert_eq!(rack.size(), 3);
+        assert!(rack.is_full());
+        assert_eq!(bag.remaining(), 99);
+    }
+
+    #[test]
+    fn exchange_leaves_tiles_marked_when_the_bag_runs_short() {
+        let mut rack = TileRack::new(0.0, 0.0, "ABC", TileRackConfig::default());
+        let mut bag = TileBag::new(42);
+        bag.draw(99);
+        rack.set_marked_for_exchange(0, true);
+        rack.set_marked_for_exchange(2, true);
+        assert_eq!(rack.exchange(&mut bag), 1);
+        assert!(!rack.tiles()[0].is_marked_for_exchange());
+        assert!(rack.tiles()[2].is_marked_for_exchange());
+        assert_eq!(&letters(&rack)[1..], "BC");
+        // The exchanged tile went back in
+        assert_eq!(bag.remaining(), 1);
+        assert!(bag.take('A'));
+    }
+
+    #[test]
+    fn arrange_with_ignores_bad_ids() {
+        let mut rack = TileRack::new(0.0, 0.0, "ABC", TileRackConfig::default());
+        // 99 isn't on the rack, 2 is given twice and 0 and 1 are left out
+        rack.arrange_with(|_| vec![99, 2, 2]);
+        assert_eq!(letters(&rack), "CAB");
+    }
+
+    #[test]
+    fn zero_animation_duration_is_instant() {
+        let config = TileRackConfig::default().with_animation_duration(Duration::ZERO);
+        let mut rack = TileRack::new(0.0, 0.0, "AB", config);
+        rack.arrange_with(|tiles| tiles.iter().rev().map(|tile| tile.id()).collect());
+        rack.advance(Duration::from_millis(16));
+        for (index, tile) in rack.tiles().iter().enumerate() {
+            assert_eq!(tile.x(), rack.get_slot_x(index));
+        }
+        assert!(!rack.is_animating());
+    }
+
+    #[test]
+    fn full_rack_leaves_tiles_on_the_board() {
+        let config = TileRackConfig::default();
+        let mut rack = TileRack::new(0.0, 100.0, "AB", config);
+        let mut board = Board::new(0.0, 0.0, 2, 1, config);
+        let (cell_x, cell_y) = board.get_cell_pos(0, 0);
+        let (press_x, press_y) = (rack.get_slot_x(0) + 10.0, 110.0);
+        rack.start_drag(0, press_x, press_y, false);
+        assert!(board.handle_mouse_up(&mut rack, cell_x + 10.0, cell_y + 10.0));
+        board.advance(Duration::from_secs(1));
+        assert_eq!(rack.size(), 1);
+
+        rack.set_capacity(Some(1));
+        assert_eq!(board.handle_mouse_down(&mut rack, cell_x + 10.0, cell_y + 10.0), BoardPress::RackFull);
+        assert!(board.get_tile(0, 0).is_some());
+        rack.set_capacity(None);
+        assert_eq!(board.handle_mouse_down(&mut rack, cell_x + 10.0, cell_y + 10.0), BoardPress::PickedUp);
+        assert_eq!(rack.size(), 2);
+    }
+}
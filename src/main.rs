@@ -1,267 +1,860 @@
 #![allow(unused_variables)]
 
 use std::env;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use ggez::graphics::Color;
-use ggez::graphics::Drawable;
 use mint::Point2;
 use itertools::Itertools;
 
-const TILE_WIDTH: f32 = 50.0;
-const TILE_HEIGHT: f32 = 50.0;
-const TILE_SPACING: f32 = 10.0;
-const TILE_COLOUR: Color = Color::new(0.9, 0.9, 0.9, 1.0);
-const ANIMATION_STEPS: i32 = 100;
-
-struct Tile {
-    x: f32,
-    y: f32,
-    letter: char,
-    blend_mode: Option<ggez::graphics::BlendMode>,
-    dragging: bool,
-    relative_x_click: Option<f32>,
-    relative_y_click: Option<f32>,
-    animation_progress: i32,
-    x_animation_step: Option<f32>,
-    y_animation_step: Option<f32>,
+use tile_rack_demo::bag::TileBag;
+use tile_rack_demo::easing::Easing;
+use tile_rack_demo::snapshot::{self, Change, Place, StateSnapshot};
+use tile_rack_demo::{rack_and_board_to_svg, Arrangement, Board, BoardPress, LabelStyle, TileRack, TileRackConfig, DEFAULT_DRAG_SMOOTHING, TILE_HEIGHT, TILE_SPACING, TILE_WIDTH};
+
+mod capture;
+mod crash;
+mod icon;
+mod ui;
+
+// Large-print mode draws everything this much bigger, with stronger colours and outlines
+const LARGE_PRINT_SCALE: f32 = 1.5;
+const BALANCE_SPACING: f32 = 15.0;
+// The bag sits to the right of the rack, as tall as a tile, and tiles dropped on it are exchanged
+const BAG_SPACING: f32 = 20.0;
+const BAG_WIDTH: f32 = 90.0;
+const BAG_COLOUR: Color = Color::new(0.6, 0.6, 0.6, 1.0);
+const BAG_EXCHANGE_COLOUR: Color = Color::new(0.8, 0.87, 1.0, 1.0);
+const BOARD_COLUMNS: usize = 9;
+const BOARD_ROWS: usize = 4;
+// Gap between the bottom of the board and the rack, leaving room for the study rack in between
+const BOARD_SPACING: f32 = 90.0;
+// Gap between the top of the window and the board
+const BOARD_MARGIN: f32 = 20.0;
+// Gap between the rack and the sorted copy shown above it in study mode
+const STUDY_SPACING: f32 = 20.0;
+const DEFAULT_IDLE_DEMO_SECS: u64 = 60;
+const DEMO_DRAG_DURATION: Duration = Duration::from_millis(700);
+const DEMO_PAUSE: Duration = Duration::from_millis(400);
+const INTRO_DRAG_DURATION: Duration = Duration::from_millis(350);
+const INTRO_PAUSE: Duration = Duration::from_millis(100);
+// How long to let the tiles fall into the rack before arranging them
+const INTRO_FALL: Duration = Duration::from_millis(700);
+// How high scripted drags lift tiles above the rack on their way across
+const DEMO_LIFT: f32 = 40.0;
+// In low-power mode, how long after the last input to slow down, and how long each frame then
+// takes (about 10 Hz)
+const LOW_POWER_DELAY: Duration = Duration::from_secs(1);
+const LOW_POWER_FRAME_TIME: Duration = Duration::from_millis(100);
+const LAYOUT_COLOUR: Color = Color::new(0.2, 0.4, 0.9, 1.0);
+// Gap between the rack and the outline drawn around it in layout editing mode
+const LAYOUT_OUTLINE_MARGIN: f32 = 6.0;
+const LAYOUT_HANDLE_SIZE: f32 = 12.0;
+const MIN_RACK_SCALE: f32 = 0.5;
+const MAX_RACK_SCALE: f32 = 3.0;
+// In slow motion (debug builds only), time passes this many times slower for the rack
+const SLOW_MOTION_FACTOR: u32 = 8;
+// How far the rack is moved on by each frame step while paused (debug builds only)
+const FRAME_STEP: Duration = Duration::from_millis(16);
+// Where Ctrl+E saves the rack and board, relative to the working directory
+const SVG_EXPORT_PATH: &str = "rack.svg";
+const CAPTURE_PATH: &str = "capture.gif";
+// How long the rack-full warning stays up after trying to pick a tile up off the board
+const RACK_FULL_NOTICE: Duration = Duration::from_secs(2);
+// How long the warning stays up after the crashed rack couldn't be put back
+const RESTORE_FAILED_NOTICE: Duration = Duration::from_secs(4);
+const VOWELS: &str = "AEIOU";
+
+/// One step of a scripted sequence of rack moves
+#[derive(Clone, Copy)]
+enum ScriptStep {
+    /// Drag the tile in slot `from` and drop it in slot `to`, taking `duration` to get there
+    Drag { from: usize, to: usize, duration: Duration },
+    /// Do nothing for a while
+    Wait(Duration),
 }
 
-impl Tile {
-    fn new(x: f32, y: f32, letter: char) -> Tile {
-        Tile {
-            x: x,
-            y: y,
-            letter: letter,
-            blend_mode: None,
-            dragging: false,
-            relative_x_click: None,
-            relative_y_click: None,
-            animation_progress: 0,
-            x_animation_step: None,
-            y_animation_step: None,
+impl ScriptStep {
+    fn duration(&self) -> Duration {
+        match self {
+            ScriptStep::Drag { duration, .. } => *duration,
+            ScriptStep::Wait(duration) => *duration,
         }
     }
-
-    fn set_pos(&mut self, x: f32, y: f32) {
-        self.x = x;
-        self.y = y;
-    }
 }
 
-impl ggez::graphics::Drawable for Tile {
-    fn draw(
-        &self,
-        ctx: &mut ggez::Context,
-        param: ggez::graphics::DrawParam,
-    ) -> ggez::GameResult {
-        let rect = ggez::graphics::Rect::new(
-            self.x, self.y,
-            TILE_WIDTH, TILE_HEIGHT,
-        );
-        let rect_drawable = ggez::graphics::Mesh::new_rectangle(
-            ctx,
-            ggez::graphics::DrawMode::fill(),
-            rect,
-            TILE_COLOUR,
-        )?;
-        ggez::graphics::draw(ctx, &rect_drawable, ggez::graphics::DrawParam::default())?;
+/// Plays script steps against a rack. Drags go through the rack's usual drag methods, so they
+/// look and animate exactly like a user's.
+struct ScriptPlayer {
+    steps: Vec<ScriptStep>,
+    current: usize,
+    step_started: Option<Duration>,
+    looping: bool,
+}
 
-        let font = ggez::graphics::Font::default();
-        let text = ggez::graphics::Text::new((self.letter, font, 24.0));
-        let text_dimensions = text.dimensions(ctx);
-        let point = Point2 {
-            x: self.x + (TILE_WIDTH / 2.0) - (text_dimensions.w / 2.0),
-            y: self.y + (TILE_HEIGHT / 2.0) - (text_dimensions.h / 2.0),
-        };
-        ggez::graphics::draw(ctx, &text, (point, Color::BLACK))?;
-        Ok(())
+impl ScriptPlayer {
+    fn new(steps: Vec<ScriptStep>, looping: bool) -> ScriptPlayer {
+        ScriptPlayer {
+            steps,
+            current: 0,
+            step_started: None,
+            looping,
+        }
     }
 
-    fn dimensions(&self, ctx: &mut ggez::Context) -> Option<ggez::graphics::Rect> {
-        Some(
-            ggez::graphics::Rect::new(
-                self.x,
-                self.y,
-                TILE_WIDTH,
-                TILE_HEIGHT,
-            )
-        )
+    /// Where the scripted pointer is `progress` of the way through dragging from slot `from` to
+    /// slot `to`: a straight line between the slot centres, lifted in the middle
+    fn get_pointer_position(rack: &TileRack, from: usize, to: usize, progress: f32) -> (f32, f32) {
+        let from_x = rack.get_slot_x(from) + TILE_WIDTH / 2.0;
+        let to_x = rack.get_slot_x(to) + TILE_WIDTH / 2.0;
+        let x = from_x + (to_x - from_x) * progress;
+        let y = rack.y() + TILE_HEIGHT / 2.0 - DEMO_LIFT * (progress * std::f32::consts::PI).sin();
+        (x, y)
     }
 
-    fn set_blend_mode(&mut self, mode: Option<ggez::graphics::BlendMode>) {
-        self.blend_mode = mode;
+    fn finished(&self) -> bool {
+        self.current >= self.steps.len()
     }
 
-    fn blend_mode(&self) -> Option<ggez::graphics::BlendMode> {
-        self.blend_mode
+    fn update(&mut self, now: Duration, rack: &mut TileRack) {
+        let step = match self.steps.get(self.current) {
+            Some(step) => *step,
+            None => return,
+        };
+        let started = match self.step_started {
+            Some(started) => started,
+            None => {
+                if let ScriptStep::Drag { from, to, .. } = step {
+                    let (x, y) = ScriptPlayer::get_pointer_position(rack, from, to, 0.0);
+                    // Scripted moves are always inserts, so they do what the script says
+                    rack.start_drag(from, x, y, false);
+                }
+                self.step_started = Some(now);
+                now
+            }
+        };
+
+        let progress = (now.saturating_sub(started).as_secs_f32() / step.duration().as_secs_f32()).min(1.0);
+        if let ScriptStep::Drag { from, to, .. } = step {
+            let (x, y) = ScriptPlayer::get_pointer_position(rack, from, to, progress);
+            rack.drag_to(x, y);
+            if progress >= 1.0 {
+                rack.end_drag();
+            }
+        }
+        if progress >= 1.0 {
+            self.step_started = None;
+            self.current += 1;
+            if self.looping && self.current >= self.steps.len() {
+                self.current = 0;
+            }
+        }
     }
 }
 
-struct TileRack {
-    x: f32,
-    y: f32,
-    tiles: Vec<Tile>,
-    size: usize,
-    blend_mode: Option<ggez::graphics::BlendMode>,
+/// A script that takes over the rack until it finishes or the user interrupts it
+struct Cutscene {
+    player: ScriptPlayer,
+    // Arrangement to leave the rack in when the cutscene ends, however it ends
+    final_order: Vec<usize>,
+    caption: &'static str,
+    // Whether just moving the mouse ends the cutscene, rather than clicking or pressing a key
+    stopped_by_motion: bool,
 }
 
-impl TileRack {
-    fn new(x: f32, y: f32, letters: &str) -> TileRack {
-        let mut tiles: Vec<Tile> = Vec::with_capacity(letters.len());
-        for (index, letter) in letters.chars().enumerate() {
-            let tile_x = x + (index as f32) * (TILE_WIDTH + TILE_SPACING);
-            let tile_y = y;
-            tiles.push(Tile::new(tile_x, tile_y, letter));
+impl Cutscene {
+    /// Intro played at startup: the tiles drop into the rack in a jumble and then sort
+    /// themselves out into the word
+    fn intro(rack: &mut TileRack) -> Cutscene {
+        let final_order = rack.get_order();
+
+        // Jumble the tiles by taking every other one, then the rest
+        let jumbled_order: Vec<usize> = final_order.iter().skip(1).step_by(2)
+            .chain(final_order.iter().step_by(2))
+            .copied()
+            .collect();
+        rack.restore_order(&jumbled_order);
+        // Start above the window; the rack's usual animation brings them down
+        rack.drop_in(-TILE_HEIGHT);
+
+        // Put each tile in place from left to right, keeping track of where the rest end up
+        let mut steps = vec![ScriptStep::Wait(INTRO_FALL)];
+        let mut order = jumbled_order;
+        for (to, id) in final_order.iter().enumerate() {
+            let from = order.iter().position(|other_id| other_id == id).unwrap();
+            if from != to {
+                let tile_id = order.remove(from);
+                order.insert(to, tile_id);
+                steps.push(ScriptStep::Drag { from, to, duration: INTRO_DRAG_DURATION });
+                steps.push(ScriptStep::Wait(INTRO_PAUSE));
+            }
         }
 
-        TileRack {
-            x: x,
-            y: y,
-            tiles: tiles,
-            size: letters.len(),
-            blend_mode: None,
+        Cutscene {
+            player: ScriptPlayer::new(steps, false),
+            final_order,
+            caption: "Press any key to skip",
+            stopped_by_motion: false,
         }
     }
 
-    fn get_dragging_tile(&self) -> Option<(usize, &Tile)> {
-        self.tiles.iter().enumerate().filter(
-            |(index, tile)| tile.dragging
-        ).next()
+    /// Attract mode shown after a while with no input: the rack plays with its own tiles until
+    /// someone touches something
+    fn idle_demo(rack: &TileRack) -> Cutscene {
+        // Reverse the rack one tile at a time, then reverse it back
+        let last = rack.size().saturating_sub(1);
+        let mut steps = Vec::new();
+        for _ in 0..2 {
+            for to in 0..last {
+                steps.push(ScriptStep::Drag { from: last, to, duration: DEMO_DRAG_DURATION });
+                steps.push(ScriptStep::Wait(DEMO_PAUSE));
+            }
+            steps.push(ScriptStep::Wait(DEMO_PAUSE * 3));
+        }
+        Cutscene {
+            player: ScriptPlayer::new(steps, true),
+            final_order: rack.get_order(),
+            caption: "Demo - press any key to play",
+            stopped_by_motion: true,
+        }
     }
+}
 
-    fn get_dragging_tile_mut(&mut self) -> Option<(usize, &mut Tile)> {
-        self.tiles.iter_mut().enumerate().filter(
-            |(index, tile)| tile.dragging
-        ).next()
-    }
+/// Vowel/consonant makeup of a rack. Anything that isn't a letter (e.g. a blank) counts as
+/// neither.
+struct RackBalance {
+    vowels: usize,
+    consonants: usize,
+}
 
-    fn get_new_tile_index(&self, x: f32) -> usize {
-        let tile_position = (x - self.x + (TILE_WIDTH / 2.0)) / (TILE_WIDTH + TILE_SPACING);
-        if tile_position < 0.0 {
-            0 as usize
-        } else if tile_position > (self.size - 1) as f32 {
-            self.size - 1
-        } else {
-            tile_position as usize
+impl RackBalance {
+    fn new(rack: &TileRack) -> RackBalance {
+        let letters = rack.tiles().iter()
+            .map(|tile| tile.letter().to_ascii_uppercase())
+            .filter(|letter| letter.is_ascii_alphabetic());
+        let (vowels, consonants): (Vec<char>, Vec<char>) = letters.partition(|letter| VOWELS.contains(*letter));
+        RackBalance {
+            vowels: vowels.len(),
+            consonants: consonants.len(),
         }
     }
 
-    fn update(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
-        // rust
-        let maybe_dragging_index_x = if let Some((dragging_index, dragging_tile)) = self.get_dragging_tile() {
-            Some((dragging_index, dragging_tile.x))
+    /// Describes what's wrong with the rack, if anything. A comfortable 7-tile rack has 2-4
+    /// vowels, so these thresholds are that ratio scaled to the rack size.
+    fn problem(&self) -> Option<&'static str> {
+        let total = self.vowels + self.consonants;
+        if total == 0 {
+            None
+        } else if self.vowels == 0 {
+            Some("no vowels")
+        } else if self.consonants == 0 {
+            Some("no consonants")
+        } else if self.vowels * 7 < total * 2 {
+            Some("too many consonants")
+        } else if self.vowels * 7 > total * 4 {
+            Some("too many vowels")
         } else {
             None
-        };
-
-        let new_tile_x_positions: Vec<f32> = (0..self.size).map(
-            |index| {
-                let mut tile_x = self.x + (index as f32) * (TILE_WIDTH + TILE_SPACING);
-                if let Some((dragging_initial_index, dragging_x)) = maybe_dragging_index_x {
-                    // new_index is the index that the tile would get if it were to be dropped now
-                    let new_index = self.get_new_tile_index(dragging_x);
-                    if new_index <= index && index <= dragging_initial_index {
-                        tile_x += TILE_WIDTH + TILE_SPACING;
-                    }
-                    else if dragging_initial_index <= index && index <= new_index {
-                        tile_x -= TILE_WIDTH + TILE_SPACING;
-                    }
-                }
-                tile_x
-            }
-        ).collect();
-
-        for (tile, new_x) in self.tiles.iter_mut().zip(new_tile_x_positions) {
-            if !tile.dragging {
-                let new_y = self.y;
-
-                let (anim_x, anim_y) = if ANIMATION_STEPS != 0 {
-                    if tile.x == new_x && tile.y == new_y || tile.animation_progress >= ANIMATION_STEPS {
-                        tile.x_animation_step = None;
-                        tile.y_animation_step = None;
-                        tile.animation_progress = 0;
-                        (new_x, new_y)
-                    }
-                    else {
-                        let x_animation_step = match tile.x_animation_step {
-                            Some(s) => s,
-                            None => (new_x - tile.x) / ANIMATION_STEPS as f32,
-                        };
-                        let y_animation_step = match tile.y_animation_step {
-                            Some(s) => s,
-                            None => (new_y - tile.y) / ANIMATION_STEPS as f32,
-                        };
-                        tile.x_animation_step = Some(x_animation_step);
-                        tile.y_animation_step = Some(y_animation_step);
-                        tile.animation_progress += 1;
-                        (tile.x + x_animation_step, tile.y + y_animation_step)
-                    }
-                } else {
-                    (new_x, new_y)
-                };
-
-                tile.set_pos(anim_x, anim_y);
-            }
         }
-        Ok(())
     }
 }
 
-impl ggez::graphics::Drawable for TileRack {
+impl ggez::graphics::Drawable for RackBalance {
     fn draw(
         &self,
         ctx: &mut ggez::Context,
         param: ggez::graphics::DrawParam,
     ) -> ggez::GameResult {
-        // Sort by t.dragging to make sure the tile being dragged gets drawn last (i.e. on top)
-        for tile in self.tiles.iter().sorted_by_key(|t| t.dragging) {
-            ggez::graphics::draw(ctx, tile, ggez::graphics::DrawParam::default())?;
-        }
-        Ok(())
+        let mut summary = format!("{} vowels / {} consonants", self.vowels, self.consonants);
+        let colour = match self.problem() {
+            Some(problem) => {
+                summary = format!("{} ({})", summary, problem);
+                Color::RED
+            },
+            None => Color::from_rgb(100, 100, 100),
+        };
+        let font = ggez::graphics::Font::default();
+        let text = ggez::graphics::Text::new((summary, font, 18.0));
+        ggez::graphics::draw(ctx, &text, param.color(colour))
     }
 
     fn dimensions(&self, ctx: &mut ggez::Context) -> Option<ggez::graphics::Rect> {
-        Some(
-            ggez::graphics::Rect::new(
-                self.x,
-                self.y,
-                (TILE_WIDTH + TILE_SPACING) * self.size as f32,
-                TILE_HEIGHT,
-            )
-        )
+        None
     }
 
-    fn set_blend_mode(&mut self, mode: Option<ggez::graphics::BlendMode>) {
-        self.blend_mode = mode;
-    }
+    fn set_blend_mode(&mut self, mode: Option<ggez::graphics::BlendMode>) {}
 
     fn blend_mode(&self) -> Option<ggez::graphics::BlendMode> {
-        self.blend_mode
+        None
     }
 }
 
+/// What the mouse is doing to the rack in layout editing mode
+#[derive(Clone, Copy)]
+enum LayoutDrag {
+    /// Moving the rack, grabbed at this offset from its top-left corner
+    Move { offset_x: f32, offset_y: f32 },
+    /// Dragging the corner handle to change the rack's scale
+    Resize,
+}
+
+/// Actions offered by a tile's context menu
+#[derive(Clone, Copy)]
+enum TileAction {
+    Lock,
+    Unlock,
+    ReturnToBag,
+    MarkForExchange,
+    UnmarkForExchange,
+    DesignateBlank,
+    Arrange(Arrangement),
+}
+
 struct State {
     rack: TileRack,
+    // Drawn in the same coordinate space as the rack
+    board: Board,
+    bag: TileBag,
+    // How many tiles the rack is topped back up to at the end of a turn
+    rack_size: usize,
+    // Tiles that have gone from the rack onto the board since the turn started, by id. They can
+    // still be taken back, since the rack isn't refilled until the turn is ended.
+    played_this_turn: Vec<usize>,
+    // Where the tiles were as of the last update, to work out what's changed since
+    last_snapshot: StateSnapshot,
+    // Open context menu and the id of the tile it was opened on, which may have moved since
+    context_menu: Option<(usize, ui::ContextMenu<TileAction>)>,
+    // Id of the blank waiting for the player to type the letter it stands for
+    designating: Option<usize>,
+    // Press being held on a tile, which opens the context menu for touch users
+    long_press: Option<(usize, ui::LongPress)>,
+    // Time of the last input event, for starting the idle demo
+    last_input: Duration,
+    idle_timeout: Option<Duration>,
+    cutscene: Option<Cutscene>,
+    // Set once the user asks to quit; the app exits when the rack has faded out
+    quitting: bool,
+    // Drop to a low frame rate while nothing is happening, to save battery
+    low_power: bool,
+    fps_cap: Option<u32>,
+    // When the last capped frame was due, for pacing the next one
+    last_frame: Duration,
+    show_debug_overlay: bool,
+    // Scale the rack is drawn at, about its top-left corner
+    rack_scale: f32,
+    // In layout editing mode the rack itself can be moved and resized instead of its tiles
+    layout_editing: bool,
+    layout_drag: Option<LayoutDrag>,
+    // Read-only alphabetical copy of the rack shown above it for study, if turned on
+    study_rack: Option<TileRack>,
+    // Debug controls for watching animations closely; the keys only work in debug builds
+    paused: bool,
+    step_requested: bool,
+    slow_motion: bool,
+    // Time as the animations see it, which stands still while paused and slows down in slow
+    // motion. Cutscenes and long presses go by it so they stay in step with the tiles.
+    sim_time: Duration,
+    // Left by the last run if it crashed, until the user has decided what to do with it
+    crash_report: Option<crash::Report>,
+    // When to stop warning that the rack is too full to take a tile back off the board
+    rack_full_until: Option<Duration>,
+    // When to stop warning that the rack from the crash couldn't be put back
+    restore_failed_until: Option<Duration>,
+    // Last few seconds on screen, if turned on, for saving when something goes wrong
+    frame_capture: Option<capture::FrameCapture>,
 }
 
 impl State {
-    fn new(rack_x: f32, rack_y: f32, letters: &str) -> State {
+    fn new(rack_x: f32, rack_y: f32, letters: &str, seed: u64) -> State {
+        let mut rack = TileRack::new(rack_x, rack_y, letters, TileRackConfig::default());
+        // Whatever the starting word is, its letters aren't in the bag any more
+        let mut bag = TileBag::new(seed);
+        for letter in letters.chars() {
+            bag.take(letter.to_ascii_uppercase());
+        }
+        let intro = Cutscene::intro(&mut rack);
+        // Centred above the rack
+        let mut board = Board::new(0.0, 0.0, BOARD_COLUMNS, BOARD_ROWS, *rack.config());
+        board.move_to(
+            rack_x + (rack.get_width() - board.get_width()) / 2.0,
+            rack_y - BOARD_SPACING - board.get_height(),
+        );
+        // Tiles played in earlier turns can't be taken back off the board once the rack has been
+        // topped back up to its starting size
+        rack.set_capacity(Some(rack.size()));
         State {
-            rack: TileRack::new(rack_x, rack_y, &letters),
+            last_snapshot: StateSnapshot::capture(&rack, &board),
+            rack_size: rack.size(),
+            played_this_turn: Vec::new(),
+            rack,
+            board,
+            bag,
+            context_menu: None,
+            designating: None,
+            long_press: None,
+            last_input: Duration::ZERO,
+            idle_timeout: Some(Duration::from_secs(DEFAULT_IDLE_DEMO_SECS)),
+            cutscene: Some(intro),
+            quitting: false,
+            low_power: false,
+            fps_cap: None,
+            last_frame: Duration::ZERO,
+            show_debug_overlay: false,
+            rack_scale: 1.0,
+            layout_editing: false,
+            layout_drag: None,
+            study_rack: None,
+            paused: false,
+            step_requested: false,
+            slow_motion: false,
+            sim_time: Duration::ZERO,
+            crash_report: None,
+            rack_full_until: None,
+            restore_failed_until: None,
+            frame_capture: None,
+        }
+    }
+
+    /// DrawParam that scales the rack about its top-left corner
+    fn get_rack_param(&self) -> ggez::graphics::DrawParam {
+        let origin = Point2 {
+            x: self.rack.x() * (1.0 - self.rack_scale),
+            y: self.rack.y() * (1.0 - self.rack_scale),
+        };
+        ggez::graphics::DrawParam::default()
+            .dest(origin)
+            .scale(mint::Vector2 { x: self.rack_scale, y: self.rack_scale })
+    }
+
+    /// Converts a point on screen to the rack's own coordinates, which are what tile positions
+    /// are in
+    fn screen_to_rack(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.rack.x() + (x - self.rack.x()) / self.rack_scale,
+            self.rack.y() + (y - self.rack.y()) / self.rack_scale,
+        )
+    }
+
+    /// Converts a point in the rack's own coordinates to a point on screen
+    fn rack_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.rack.x() + (x - self.rack.x()) * self.rack_scale,
+            self.rack.y() + (y - self.rack.y()) * self.rack_scale,
+        )
+    }
+
+    /// The area the rack covers on screen
+    fn get_rack_screen_rect(&self) -> ggez::graphics::Rect {
+        ggez::graphics::Rect::new(
+            self.rack.x(),
+            self.rack.y(),
+            self.rack.get_width() * self.rack_scale,
+            TILE_HEIGHT * self.rack_scale,
+        )
+    }
+
+    /// Where tiles can be dropped to exchange them, in the rack's coordinates
+    fn get_bag_rect(&self) -> ggez::graphics::Rect {
+        ggez::graphics::Rect::new(
+            self.rack.x() + self.rack.get_width() + BAG_SPACING,
+            self.rack.y(),
+            BAG_WIDTH,
+            TILE_HEIGHT,
+        )
+    }
+
+    /// Number of tiles waiting to go back in the bag
+    fn get_exchange_count(&self) -> usize {
+        self.rack.tiles().iter().filter(|tile| tile.is_marked_for_exchange()).count()
+    }
+
+    /// The bag, with the number of tiles left in it, highlighted while an exchange is waiting to
+    /// be confirmed
+    fn draw_bag(&self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let rect = self.get_bag_rect();
+        let (x, y) = self.rack_to_screen(rect.x, rect.y);
+        let (right, bottom) = self.rack_to_screen(rect.right(), rect.bottom());
+        let screen_rect = ggez::graphics::Rect::new(x, y, right - x, bottom - y);
+        if self.get_exchange_count() > 0 {
+            let highlight = ggez::graphics::Mesh::new_rectangle(
+                ctx,
+                ggez::graphics::DrawMode::fill(),
+                screen_rect,
+                BAG_EXCHANGE_COLOUR,
+            )?;
+            ggez::graphics::draw(ctx, &highlight, ggez::graphics::DrawParam::default())?;
+        }
+        let outline = ggez::graphics::Mesh::new_rectangle(
+            ctx,
+            ggez::graphics::DrawMode::stroke(2.0),
+            screen_rect,
+            BAG_COLOUR,
+        )?;
+        ggez::graphics::draw(ctx, &outline, ggez::graphics::DrawParam::default())?;
+
+        let font = ggez::graphics::Font::default();
+        let count = ggez::graphics::Text::new((format!("Bag: {}", self.bag.remaining()), font, 18.0));
+        let point = Point2 {
+            x: screen_rect.x + (screen_rect.w - count.width(ctx)) / 2.0,
+            y: screen_rect.y + (screen_rect.h - count.height(ctx)) / 2.0,
+        };
+        ggez::graphics::draw(ctx, &count, (point, Color::from_rgb(100, 100, 100)))
+    }
+
+    /// Works out what a click at `x`, `y` grabs in layout editing mode, if anything
+    fn get_layout_drag_at(&self, x: f32, y: f32) -> Option<LayoutDrag> {
+        let rect = self.get_rack_screen_rect();
+        let handle_x = rect.right() + LAYOUT_OUTLINE_MARGIN;
+        let handle_y = rect.bottom() + LAYOUT_OUTLINE_MARGIN;
+        if (x - handle_x).abs() <= LAYOUT_HANDLE_SIZE && (y - handle_y).abs() <= LAYOUT_HANDLE_SIZE {
+            Some(LayoutDrag::Resize)
+        } else if rect.contains(Point2 { x, y }) {
+            Some(LayoutDrag::Move { offset_x: x - rect.x, offset_y: y - rect.y })
+        } else {
+            None
+        }
+    }
+
+    /// Outline and resize handle shown around the rack in layout editing mode
+    fn draw_layout_outline(&self, ctx: &mut ggez::Context) -> ggez::GameResult {
+        let mut rect = self.get_rack_screen_rect();
+        rect.x -= LAYOUT_OUTLINE_MARGIN;
+        rect.y -= LAYOUT_OUTLINE_MARGIN;
+        rect.w += LAYOUT_OUTLINE_MARGIN * 2.0;
+        rect.h += LAYOUT_OUTLINE_MARGIN * 2.0;
+        let outline = ggez::graphics::Mesh::new_rectangle(
+            ctx,
+            ggez::graphics::DrawMode::stroke(2.0),
+            rect,
+            LAYOUT_COLOUR,
+        )?;
+        ggez::graphics::draw(ctx, &outline, ggez::graphics::DrawParam::default())?;
+
+        let handle_rect = ggez::graphics::Rect::new(
+            rect.right() - LAYOUT_HANDLE_SIZE / 2.0,
+            rect.bottom() - LAYOUT_HANDLE_SIZE / 2.0,
+            LAYOUT_HANDLE_SIZE,
+            LAYOUT_HANDLE_SIZE,
+        );
+        let handle = ggez::graphics::Mesh::new_rectangle(
+            ctx,
+            ggez::graphics::DrawMode::fill(),
+            handle_rect,
+            LAYOUT_COLOUR,
+        )?;
+        ggez::graphics::draw(ctx, &handle, ggez::graphics::DrawParam::default())
+    }
+
+    /// Whether the app can safely run at a low frame rate: no recent input and nothing on screen
+    /// that's moving
+    fn is_idle(&self, now: Duration) -> bool {
+        now.saturating_sub(self.last_input) >= LOW_POWER_DELAY
+            && !self.rack.is_animating()
+            && !self.board.is_animating()
+            && !self.study_rack.as_ref().is_some_and(|study_rack| study_rack.is_animating())
+            && self.cutscene.is_none()
+            && self.long_press.is_none()
+            && !self.quitting
+    }
+
+    /// Fades the rack out, after which `update` quits. Unpauses the debug pause, since the fade
+    /// would never finish otherwise.
+    fn start_quitting(&mut self) {
+        self.quitting = true;
+        self.paused = false;
+        self.rack.fade_out();
+    }
+
+    /// Ends the running cutscene, if any, leaving the rack how the cutscene wanted it
+    fn stop_cutscene(&mut self) {
+        if let Some(cutscene) = self.cutscene.take() {
+            self.rack.cancel_drag();
+            self.rack.restore_order(&cutscene.final_order);
+        }
+    }
+
+    /// Notes that the user did something. Returns true if a cutscene is running, in which case
+    /// the event shouldn't be handled any further; unless it's just mouse motion that the
+    /// cutscene ignores, the cutscene is stopped too.
+    fn register_input(&mut self, ctx: &mut ggez::Context, is_motion: bool) -> bool {
+        self.last_input = ggez::timer::time_since_start(ctx);
+        match &self.cutscene {
+            Some(cutscene) if is_motion && !cutscene.stopped_by_motion => true,
+            Some(_) => {
+                self.stop_cutscene();
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// How far to move the rack on this frame, taking the debug pause and slow motion into
+    /// account
+    fn get_time_step(&mut self, ctx: &mut ggez::Context) -> Duration {
+        if self.paused {
+            if std::mem::take(&mut self.step_requested) { FRAME_STEP } else { Duration::ZERO }
+        } else if self.slow_motion {
+            ggez::timer::delta(ctx) / SLOW_MOTION_FACTOR
+        } else {
+            ggez::timer::delta(ctx)
+        }
+    }
+
+    /// Works out what's happened to the tiles since the last update. Changes go in the crash log,
+    /// and tiles moving between the rack and the board are counted towards this turn's play.
+    fn track_changes(&mut self) {
+        let snapshot = StateSnapshot::capture(&self.rack, &self.board);
+        let changes = snapshot::diff(&self.last_snapshot, &snapshot);
+        for change in changes.iter() {
+            // Shuffling the rack around is too common to be worth the space in the log
+            if !matches!(change, Change::TileMoved { from: Place::Rack(_), to: Place::Rack(_), .. }) {
+                crash::record_change(format!("{:?}", change));
+            }
+            match change {
+                Change::TileMoved { id, from: Place::Rack(_), to: Place::Board { .. } } => {
+                    self.played_this_turn.push(*id);
+                },
+                Change::TileMoved { id, from: Place::Board { .. }, to: Place::Rack(_) } => {
+                    self.played_this_turn.retain(|played_id| played_id != id);
+                },
+                _ => (),
+            }
+        }
+        self.last_snapshot = snapshot;
+    }
+
+    /// Tops the rack back up from the bag, if any tiles were played this turn. The new tiles show
+    /// up as added in the next update's changes.
+    fn end_turn(&mut self) {
+        if !self.played_this_turn.is_empty() {
+            self.rack.refill(&mut self.bag, self.rack_size);
+            self.played_this_turn.clear();
+        }
+    }
+
+    /// Keeps the study rack showing the same letters as the rack, sorted, and sitting just above it
+    fn sync_study_rack(&mut self) {
+        if let Some(study_rack) = &mut self.study_rack {
+            let letters: String = self.rack.tiles().iter().map(|tile| tile.letter()).sorted().collect();
+            let study_letters: String = study_rack.tiles().iter().map(|tile| tile.letter()).collect();
+            let (x, y) = (self.rack.x(), self.rack.y() - TILE_HEIGHT - STUDY_SPACING);
+            if letters != study_letters {
+                *study_rack = TileRack::new(x, y, &letters, TileRackConfig::default());
+                study_rack.set_label_style(self.rack.label_style());
+                study_rack.set_high_contrast(self.rack.high_contrast());
+            } else if (study_rack.x(), study_rack.y()) != (x, y) {
+                study_rack.move_to(x, y);
+            }
+        }
+    }
+
+    /// Puts the rack back to `letters`, as saved in a crash dump. If the letters are different
+    /// from the ones on the rack, the rack's tiles go back in the bag and the dumped letters are
+    /// taken out instead. Returns false and leaves everything alone if the bag doesn't have them.
+    fn restore_rack(&mut self, letters: &str) -> bool {
+        if self.rack.restore_letters(letters) {
+            return true;
+        }
+        let current_letters: Vec<char> = self.rack.tiles().iter().map(|tile| tile.letter()).collect();
+        for letter in current_letters.iter() {
+            self.bag.put_back(*letter);
+        }
+        let mut taken = Vec::new();
+        for letter in letters.chars() {
+            if !self.bag.take(letter) {
+                // Not enough of that letter left, so put the bag back how it was
+                for letter in taken {
+                    self.bag.put_back(letter);
+                }
+                for letter in current_letters {
+                    self.bag.take(letter);
+                }
+                return false;
+            }
+            taken.push(letter);
+        }
+        self.rack.set_letters(letters);
+        true
+    }
+
+    fn open_context_menu(&mut self, tile_index: usize, x: f32, y: f32) {
+        let lock_item = if self.rack.tiles()[tile_index].is_locked() {
+            (icon::Icon::Unlock, "Unlock".to_owned(), TileAction::Unlock)
+        } else {
+            (icon::Icon::Lock, "Lock".to_owned(), TileAction::Lock)
+        };
+        let exchange_item = if self.rack.tiles()[tile_index].is_marked_for_exchange() {
+            (icon::Icon::Exchange, "Don't exchange".to_owned(), TileAction::UnmarkForExchange)
+        } else {
+            (icon::Icon::Exchange, "Mark for exchange".to_owned(), TileAction::MarkForExchange)
+        };
+        let mut items = vec![
+            lock_item,
+            (icon::Icon::Bag, "Return to bag".to_owned(), TileAction::ReturnToBag),
+            exchange_item,
+        ];
+        if self.rack.tiles()[tile_index].is_blank() {
+            items.push((icon::Icon::Designate, "Designate blank".to_owned(), TileAction::DesignateBlank));
+        }
+        items.extend(
+            Arrangement::ALL.iter().map(
+                |arrangement| (icon::Icon::Sort, arrangement.name().to_owned(), TileAction::Arrange(*arrangement))
+            )
+        );
+        let tile_id = self.rack.tiles()[tile_index].id();
+        self.context_menu = Some((tile_id, ui::ContextMenu::new(x, y, items)));
+    }
+
+    /// Applies an action picked from the context menu of the tile with id `tile_id`. Does nothing
+    /// if the tile has left the rack since the menu was opened.
+    fn apply_tile_action(&mut self, tile_id: usize, action: TileAction) {
+        let tile_index = match self.rack.tiles().iter().position(|tile| tile.id() == tile_id) {
+            Some(tile_index) => tile_index,
+            None => return,
+        };
+        match action {
+            TileAction::Lock => self.rack.set_locked(tile_index, true),
+            TileAction::Unlock => self.rack.set_locked(tile_index, false),
+            TileAction::ReturnToBag => {
+                let tile = self.rack.remove_tile(tile_index);
+                self.bag.put_back(tile.letter());
+            },
+            TileAction::MarkForExchange => self.rack.set_marked_for_exchange(tile_index, true),
+            TileAction::UnmarkForExchange => self.rack.set_marked_for_exchange(tile_index, false),
+            // The letter comes from the next text input
+            TileAction::DesignateBlank => self.designating = Some(tile_id),
+            TileAction::Arrange(arrangement) => self.rack.arrange(arrangement),
         }
     }
 }
 
 impl ggez::event::EventHandler<ggez::GameError> for State {
     fn update(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
-        while ggez::timer::check_update_time(ctx, 500) {
-            self.rack.update(ctx)?;
+        let time_step = self.get_time_step(ctx);
+        self.sim_time += time_step;
+        self.rack.advance(time_step);
+        self.board.advance(time_step);
+        let letters: String = self.rack.tiles().iter().map(|tile| tile.letter()).collect();
+        crash::record_rack(&letters);
+        self.track_changes();
+        self.sync_study_rack();
+        if self.quitting && !self.rack.is_fading() {
+            ggez::event::quit(ctx);
+        }
+
+        let now = ggez::timer::time_since_start(ctx);
+        if let Some(cutscene) = &mut self.cutscene {
+            cutscene.player.update(self.sim_time, &mut self.rack);
+            if cutscene.player.finished() {
+                self.stop_cutscene();
+                // Count idle time from the end of the cutscene, not from the last real input
+                self.last_input = now;
+            }
+        } else if let Some(idle_timeout) = self.idle_timeout {
+            // Don't start while the user is in the middle of something
+            let busy = self.rack.dragging_index().is_some()
+                || self.context_menu.is_some()
+                || self.designating.is_some()
+                || self.layout_editing;
+            if !busy && now.saturating_sub(self.last_input) >= idle_timeout {
+                self.cutscene = Some(Cutscene::idle_demo(&self.rack));
+            }
+        }
+
+        let long_press_done = match &mut self.long_press {
+            Some((_, long_press)) => long_press.update(self.sim_time),
+            None => false,
+        };
+        if long_press_done {
+            if let Some((tile_index, long_press)) = self.long_press.take() {
+                // The tile was picked up by the press, so put it back before showing the menu
+                self.rack.cancel_drag();
+                self.open_context_menu(tile_index, long_press.x(), long_press.y());
+            }
+        }
+
+        // ggez polls continuously, so the only way to slow down is to sleep. Input that arrives
+        // in the meantime resets last_input, so the next frame is back to full speed.
+        if self.low_power && self.is_idle(now) {
+            ggez::timer::sleep(LOW_POWER_FRAME_TIME);
+        }
+
+        if let Some(fps_cap) = self.fps_cap {
+            // Sleep off whatever is left of this frame's time slot
+            let frame_time = Duration::from_secs_f64(1.0 / fps_cap as f64);
+            let next_frame = self.last_frame + frame_time;
+            let now = ggez::timer::time_since_start(ctx);
+            if now < next_frame {
+                ggez::timer::sleep(next_frame - now);
+            }
+            self.last_frame = now.max(next_frame);
         }
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
         ggez::graphics::clear(ctx, Color::WHITE);
-        ggez::graphics::draw(ctx, &self.rack, ggez::graphics::DrawParam::default())?;
+        ggez::graphics::draw(ctx, &self.board, self.get_rack_param())?;
+        if let Some(study_rack) = &self.study_rack {
+            ggez::graphics::draw(ctx, study_rack, self.get_rack_param())?;
+        }
+        ggez::graphics::draw(ctx, &self.rack, self.get_rack_param())?;
+        if self.layout_editing {
+            self.draw_layout_outline(ctx)?;
+        }
+
+        // Recomputed every frame so it follows the rack as tiles change
+        let balance = RackBalance::new(&self.rack);
+        let (balance_x, balance_y) = self.rack_to_screen(self.rack.x(), self.rack.y() + TILE_HEIGHT);
+        let balance_point = Point2 {
+            x: balance_x,
+            y: balance_y + BALANCE_SPACING,
+        };
+        ggez::graphics::draw(ctx, &balance, (balance_point,))?;
+        self.draw_bag(ctx)?;
+
+        if let Some((_, long_press)) = &self.long_press {
+            ggez::graphics::draw(ctx, long_press, ggez::graphics::DrawParam::default())?;
+        }
+        if let Some((_, menu)) = &self.context_menu {
+            ggez::graphics::draw(ctx, menu, ggez::graphics::DrawParam::default())?;
+        }
+        let caption = match &self.cutscene {
+            _ if self.crash_report.is_some() => Some("Sorry, the demo crashed last time. Press R to restore your rack, D to find the crash report, or any other key to carry on"),
+            Some(cutscene) => Some(cutscene.caption),
+            None if self.restore_failed_until.is_some_and(|until| ggez::timer::time_since_start(ctx) < until) => {
+                Some("Sorry, your rack couldn't be restored")
+            },
+            None if self.designating.is_some() => Some("Type the letter this blank stands for, or press Esc to cancel"),
+            None if self.layout_editing => Some("Drag the rack to move it, or its corner to resize it. Press F2 when done"),
+            None if self.rack_full_until.is_some_and(|until| ggez::timer::time_since_start(ctx) < until) => {
+                Some("The rack is full, so that tile has to stay on the board")
+            },
+            None if self.get_exchange_count() > 0 => Some("Click the bag to exchange the raised tiles, or press Esc to keep them"),
+            None if !self.played_this_turn.is_empty() => Some("Press Space to end your turn and draw new tiles"),
+            None => None,
+        };
+        if let Some(caption) = caption {
+            let font = ggez::graphics::Font::default();
+            let text = ggez::graphics::Text::new((caption, font, 18.0));
+            let point = Point2 { x: 10.0, y: 10.0 };
+            ggez::graphics::draw(ctx, &text, (point, Color::from_rgb(100, 100, 100)))?;
+        }
+        if self.show_debug_overlay || self.paused || self.slow_motion {
+            let font = ggez::graphics::Font::default();
+            let mut overlay = format!("FPS: {:.1}", ggez::timer::fps(ctx));
+            if self.paused {
+                overlay += " (paused)";
+            } else if self.slow_motion {
+                overlay += " (slow motion)";
+            }
+            let text = ggez::graphics::Text::new((overlay, font, 18.0));
+            let (window_width, _) = ggez::graphics::drawable_size(ctx);
+            let point = Point2 { x: window_width - text.width(ctx) - 10.0, y: 10.0 };
+            ggez::graphics::draw(ctx, &text, (point, Color::from_rgb(100, 100, 100)))?;
+        }
+        if let Some(frame_capture) = &mut self.frame_capture {
+            frame_capture.capture(ctx)?;
+        }
         ggez::graphics::present(ctx)
     }
 
@@ -272,25 +865,207 @@ impl ggez::event::EventHandler<ggez::GameError> for State {
         x: f32,
         y: f32,
     ) {
+        crash::record_input(format!("mouse down {:?} at ({}, {})", button, x, y));
+        if self.register_input(ctx, false) {
+            return;
+        }
+
+        if self.layout_editing {
+            if button == ggez::input::mouse::MouseButton::Left {
+                self.layout_drag = self.get_layout_drag_at(x, y);
+            }
+            return;
+        }
+
+        // Any click while a menu is open either picks an item or dismisses the menu
+        if let Some((tile_id, menu)) = self.context_menu.take() {
+            if button == ggez::input::mouse::MouseButton::Left {
+                if let Some(action) = menu.item_at(x, y) {
+                    self.apply_tile_action(tile_id, action);
+                }
+            }
+            return;
+        }
+
+        let (rack_x, rack_y) = self.screen_to_rack(x, y);
         if button == ggez::input::mouse::MouseButton::Left {
-            let click_point = Point2{x, y};
-            // Approximate tile position (doesn't take into account y position, spacing or
-            // coordinates to the left/right of the tile rack)
-            let tile_position = ((x - self.rack.x) / (TILE_WIDTH + TILE_SPACING)) as usize;
-            if tile_position <= self.rack.size - 1 {
-                let tile = &mut self.rack.tiles[tile_position];
-                // Check if mouse event was actually within the bounds of the tile
-                if let Some(tile_bounds) = tile.dimensions(ctx) {
-                    if tile_bounds.contains(click_point) {
-                        tile.dragging = true;
-                        tile.relative_x_click = Some(x - tile.x);
-                        tile.relative_y_click = Some(y - tile.y);
+            let on_bag = self.get_bag_rect().contains(Point2 { x: rack_x, y: rack_y });
+            if on_bag && self.get_exchange_count() > 0 {
+                self.rack.exchange(&mut self.bag);
+                return;
+            }
+            match self.board.handle_mouse_down(&mut self.rack, rack_x, rack_y) {
+                // Picked up off the board and now being dragged as part of the rack
+                BoardPress::PickedUp => self.rack.set_focus(None),
+                BoardPress::RackFull => {
+                    self.rack_full_until = Some(ggez::timer::time_since_start(ctx) + RACK_FULL_NOTICE);
+                },
+                BoardPress::Missed => {
+                    if let Some(tile_index) = self.rack.handle_mouse_down(rack_x, rack_y) {
+                        self.long_press = Some((tile_index, ui::LongPress::new(x, y, self.sim_time)));
+                    }
+                },
+            }
+        } else if button == ggez::input::mouse::MouseButton::Right {
+            if let Some(tile_index) = self.rack.get_tile_index_at(rack_x, rack_y) {
+                self.rack.set_focus(Some(tile_index));
+                self.open_context_menu(tile_index, x, y);
+            }
+        }
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut ggez::Context,
+        keycode: ggez::input::keyboard::KeyCode,
+        keymods: ggez::input::keyboard::KeyMods,
+        repeat: bool,
+    ) {
+        use ggez::input::keyboard::{KeyCode, KeyMods};
+
+        crash::record_input(format!("key down {:?} {:?}", keycode, keymods));
+        let stopped_cutscene = self.register_input(ctx, false);
+        if let Some(report) = self.crash_report.take() {
+            match keycode {
+                KeyCode::R => {
+                    let restored = report.rack().is_some_and(|letters| self.restore_rack(letters));
+                    if !restored {
+                        self.restore_failed_until = Some(ggez::timer::time_since_start(ctx) + RESTORE_FAILED_NOTICE);
+                    }
+                },
+                KeyCode::D => {
+                    report.reveal();
+                    // Keep offering to restore the rack
+                    self.crash_report = Some(report);
+                },
+                _ => (),
+            }
+            return;
+        }
+        if stopped_cutscene {
+            return;
+        }
+
+        // Keys are letters for the blank until one is typed, so they don't do anything else
+        if self.designating.is_some() {
+            if keycode == KeyCode::Escape {
+                self.designating = None;
+            }
+            return;
+        }
+
+        let forward = !keymods.contains(KeyMods::SHIFT);
+        if let Some((tile_id, menu)) = &mut self.context_menu {
+            let tile_id = *tile_id;
+            match keycode {
+                KeyCode::Tab => menu.focus_next(forward),
+                KeyCode::Down => menu.focus_next(true),
+                KeyCode::Up => menu.focus_next(false),
+                KeyCode::Return | KeyCode::NumpadEnter => {
+                    if let Some(action) = menu.focused_item() {
+                        self.apply_tile_action(tile_id, action);
+                    }
+                    self.context_menu = None;
+                },
+                KeyCode::Escape => self.context_menu = None,
+                _ => (),
+            }
+            return;
+        }
+
+        match keycode {
+            KeyCode::F2 => {
+                self.layout_editing = !self.layout_editing;
+                self.layout_drag = None;
+                self.long_press = None;
+                self.rack.cancel_drag();
+            },
+            KeyCode::F3 => self.show_debug_overlay = !self.show_debug_overlay,
+            KeyCode::E if keymods.contains(KeyMods::CTRL) => {
+                match std::fs::write(SVG_EXPORT_PATH, rack_and_board_to_svg(&self.rack, &self.board)) {
+                    Ok(()) => println!("Saved the rack and board to {}", SVG_EXPORT_PATH),
+                    Err(error) => eprintln!("Couldn't save the rack and board to {}: {}", SVG_EXPORT_PATH, error),
+                }
+            },
+            KeyCode::F8 => {
+                if let Some(frame_capture) = &self.frame_capture {
+                    match frame_capture.save(std::path::Path::new(CAPTURE_PATH)) {
+                        Ok(()) => println!("Saved the last few seconds to {}", CAPTURE_PATH),
+                        Err(error) => eprintln!("Couldn't save the last few seconds to {}: {}", CAPTURE_PATH, error),
+                    }
+                }
+            },
+            KeyCode::F4 => {
+                self.study_rack = match self.study_rack {
+                    Some(_) => None,
+                    None => Some(TileRack::new(self.rack.x(), self.rack.y(), "", TileRackConfig::default())),
+                };
+                self.sync_study_rack();
+            },
+            KeyCode::F5 if cfg!(debug_assertions) => self.paused = !self.paused,
+            KeyCode::F6 if cfg!(debug_assertions) => self.step_requested = true,
+            KeyCode::F7 if cfg!(debug_assertions) => self.slow_motion = !self.slow_motion,
+            KeyCode::Tab => self.rack.cycle_focus(forward),
+            KeyCode::Space => self.end_turn(),
+            KeyCode::Left if keymods.contains(KeyMods::CTRL) => {
+                if let Some(index) = self.rack.get_focused_tile_index() {
+                    self.rack.move_focused_tile(index.saturating_sub(1));
+                }
+            },
+            KeyCode::Right if keymods.contains(KeyMods::CTRL) => {
+                if let Some(index) = self.rack.get_focused_tile_index() {
+                    self.rack.move_focused_tile(index + 1);
+                }
+            },
+            KeyCode::Home => self.rack.move_focused_tile(0),
+            KeyCode::End => self.rack.move_focused_tile(self.rack.size().saturating_sub(1)),
+            KeyCode::Return | KeyCode::NumpadEnter => {
+                if let Some(tile_index) = self.rack.get_focused_tile_index() {
+                    // Open the menu over the tile, with its first item ready to pick
+                    let tile = &self.rack.tiles()[tile_index];
+                    let (menu_x, menu_y) = self.rack_to_screen(tile.x() + TILE_WIDTH / 2.0, tile.y() + TILE_HEIGHT / 2.0);
+                    self.open_context_menu(tile_index, menu_x, menu_y);
+                    if let Some((_, menu)) = &mut self.context_menu {
+                        menu.focus_next(true);
                     }
                 }
+            },
+            KeyCode::Escape => {
+                if self.get_exchange_count() > 0 {
+                    for index in 0..self.rack.size() {
+                        self.rack.set_marked_for_exchange(index, false);
+                    }
+                } else if self.rack.get_focused_tile_index().is_some() {
+                    self.rack.set_focus(None);
+                } else {
+                    self.start_quitting();
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn text_input_event(&mut self, ctx: &mut ggez::Context, character: char) {
+        if !character.is_alphabetic() {
+            return;
+        }
+        if let Some(tile_id) = self.designating.take() {
+            // The blank may have been played or returned to the bag in the meantime
+            if let Some(tile_index) = self.rack.tiles().iter().position(|tile| tile.id() == tile_id) {
+                self.rack.designate_blank(tile_index, Some(character));
             }
         }
     }
 
+    fn quit_event(&mut self, ctx: &mut ggez::Context) -> bool {
+        // Closing the window again while fading out quits straight away
+        if self.quitting {
+            return false;
+        }
+        self.start_quitting();
+        true
+    }
+
     fn mouse_motion_event(
         &mut self,
         ctx: &mut ggez::Context,
@@ -299,13 +1074,36 @@ impl ggez::event::EventHandler<ggez::GameError> for State {
         dx: f32,
         dy: f32,
     ) {
-        for (index, tile) in self.rack.tiles.iter_mut().enumerate() {
-            if tile.dragging {
-                let tile_x = x - tile.relative_x_click.unwrap();
-                let tile_y = y - tile.relative_y_click.unwrap();
-                tile.set_pos(tile_x, tile_y);
+        if self.register_input(ctx, true) {
+            return;
+        }
+
+        match self.layout_drag {
+            Some(LayoutDrag::Move { offset_x, offset_y }) => {
+                // The board comes along, keeping its place above the rack
+                let (dx, dy) = (x - offset_x - self.rack.x(), y - offset_y - self.rack.y());
+                self.board.move_to(self.board.x() + dx, self.board.y() + dy);
+                self.rack.move_to(x - offset_x, y - offset_y);
+            },
+            Some(LayoutDrag::Resize) => {
+                // Scale to whichever of the pointer's distances from the corner asks for more
+                let scale_x = (x - self.rack.x()) / self.rack.get_width();
+                let scale_y = (y - self.rack.y()) / TILE_HEIGHT;
+                self.rack_scale = scale_x.max(scale_y).clamp(MIN_RACK_SCALE, MAX_RACK_SCALE);
+            },
+            None => (),
+        }
+
+        if let Some((_, menu)) = &mut self.context_menu {
+            menu.hover(x, y);
+        }
+        if let Some((_, long_press)) = &self.long_press {
+            if !long_press.within_tolerance(x, y) {
+                self.long_press = None;
             }
         }
+        let (rack_x, rack_y) = self.screen_to_rack(x, y);
+        self.rack.handle_mouse_motion(rack_x, rack_y);
     }
 
     fn mouse_button_up_event(
@@ -315,35 +1113,124 @@ impl ggez::event::EventHandler<ggez::GameError> for State {
         x: f32,
         y: f32,
     ) {
+        crash::record_input(format!("mouse up {:?} at ({}, {})", button, x, y));
+        if self.register_input(ctx, false) {
+            return;
+        }
+
         if button == ggez::input::mouse::MouseButton::Left {
-            // assume there is only one tile being dragged
-            let maybe_dragging_index_x = if let Some((index, tile)) = self.rack.get_dragging_tile_mut() {
-                tile.dragging = false;
-                Some((index, tile.x))
+            self.layout_drag = None;
+            self.long_press = None;
+            let (rack_x, rack_y) = self.screen_to_rack(x, y);
+            let on_bag = self.get_bag_rect().contains(Point2 { x: rack_x, y: rack_y });
+            if let Some(index) = self.rack.dragging_index().filter(|_| on_bag) {
+                // The tile goes back to its slot, raised, until the exchange is confirmed
+                self.rack.cancel_drag();
+                self.rack.set_marked_for_exchange(index, true);
+            } else if !self.board.handle_mouse_up(&mut self.rack, rack_x, rack_y) {
+                self.rack.handle_mouse_up(rack_x, rack_y);
+            }
+        }
+    }
+}
+
+/// Command line options:
+/// `tile_rack_demo [--drag-smoothing=<0-1>] [--idle-demo=<seconds>] [--jitter] [--low-power]
+/// [--no-vsync] [--fps-cap=<fps>] [--insert-only] [--animation-speed=<0.25-4|instant>]
+/// [--lowercase] [--font-size=<points>] [--large-print]
+/// [--easing=<linear|ease-out-cubic|ease-in-out|elastic|bounce>] [--capture] [WORD]`
+struct Options {
+    letters: String,
+    drag_smoothing: f32,
+    jitter: bool,
+    low_power: bool,
+    vsync: bool,
+    fps_cap: Option<u32>,
+    swap_on_drop: bool,
+    // None if animations are instant
+    animation_speed: Option<f32>,
+    easing: Easing,
+    label_style: LabelStyle,
+    large_print: bool,
+    capture: bool,
+    // None if the idle demo is turned off
+    idle_timeout: Option<Duration>,
+}
+
+impl Options {
+    fn from_args() -> Options {
+        let mut options = Options {
+            letters: "AEINRST".to_owned(),
+            drag_smoothing: DEFAULT_DRAG_SMOOTHING,
+            jitter: false,
+            low_power: false,
+            vsync: true,
+            fps_cap: None,
+            swap_on_drop: true,
+            animation_speed: Some(1.0),
+            easing: Easing::Linear,
+            label_style: LabelStyle::default(),
+            large_print: false,
+            capture: false,
+            idle_timeout: Some(Duration::from_secs(DEFAULT_IDLE_DEMO_SECS)),
+        };
+        for arg in env::args().skip(1) {
+            if let Some(value) = arg.strip_prefix("--drag-smoothing=") {
+                options.drag_smoothing = value.parse()
+                    .expect("--drag-smoothing should be a number from 0 to 1");
+            } else if let Some(value) = arg.strip_prefix("--idle-demo=") {
+                let secs: u64 = value.parse()
+                    .expect("--idle-demo should be a whole number of seconds");
+                options.idle_timeout = if secs == 0 { None } else { Some(Duration::from_secs(secs)) };
+            } else if let Some(value) = arg.strip_prefix("--animation-speed=") {
+                options.animation_speed = if value == "instant" {
+                    None
+                } else {
+                    Some(value.parse().expect("--animation-speed should be a number from 0.25 to 4, or \"instant\""))
+                };
+            } else if let Some(value) = arg.strip_prefix("--easing=") {
+                options.easing = value.parse()
+                    .expect("--easing should be linear, ease-out-cubic, ease-in-out, elastic or bounce");
+            } else if arg == "--capture" {
+                options.capture = true;
+            } else if arg == "--large-print" {
+                options.large_print = true;
+            } else if arg == "--lowercase" {
+                options.label_style.lowercase = true;
+            } else if let Some(value) = arg.strip_prefix("--font-size=") {
+                options.label_style.font_size = value.parse()
+                    .expect("--font-size should be a number of points");
+            } else if arg == "--jitter" {
+                options.jitter = true;
+            } else if arg == "--low-power" {
+                options.low_power = true;
+            } else if arg == "--insert-only" {
+                options.swap_on_drop = false;
+            } else if arg == "--no-vsync" {
+                options.vsync = false;
+            } else if let Some(value) = arg.strip_prefix("--fps-cap=") {
+                let fps: u32 = value.parse()
+                    .expect("--fps-cap should be a whole number of frames per second");
+                options.fps_cap = if fps == 0 { None } else { Some(fps) };
             } else {
-                None
-            };
-            if let Some((index, tile_x)) = maybe_dragging_index_x {
-                let new_index = self.rack.get_new_tile_index(tile_x);
-                let tile_deref = self.rack.tiles.remove(index);
-                self.rack.tiles.insert(new_index, tile_deref);
+                options.letters = arg;
             }
         }
+        options
     }
 }
 
 fn main() {
-    let mut args: Vec<String> = env::args().collect();
-    let rack_str = match args.get(1) {
-        Some(arg) => args.remove(1),
-        None => "AEINRST".to_owned(),
-    };
+    // Bad options are the user's mistake, not a crash worth reporting
+    let options = Options::from_args();
+    crash::install();
+    let rack_str = options.letters;
 
     let conf = ggez::conf::Conf {
         window_setup: ggez::conf::WindowSetup {
             title: "Tile rack demo".to_owned(),
             samples: ggez::conf::NumSamples::One,
-            vsync: true,
+            vsync: options.vsync,
             icon: "".to_owned(),
             srgb: true,
         },
@@ -352,19 +1239,41 @@ fn main() {
         modules: ggez::conf::ModuleConf::default(),
     };
     let window_width = conf.window_mode.width;
-    let window_height = conf.window_mode.height;
     let (ctx, event_loop) = ggez::ContextBuilder::new("tile_rack_demo", "david")
         .default_conf(conf)
         .build()
         .unwrap();
 
-    let rack_width = (TILE_WIDTH + TILE_SPACING) * (rack_str.len() as f32) - TILE_SPACING;
-    let rack_height = TILE_HEIGHT;
+    let rack_scale = if options.large_print { LARGE_PRINT_SCALE } else { 1.0 };
+    let rack_width = ((TILE_WIDTH + TILE_SPACING) * (rack_str.chars().count() as f32) - TILE_SPACING) * rack_scale;
+    let board_height = Board::new(0.0, 0.0, BOARD_COLUMNS, BOARD_ROWS, TileRackConfig::default()).get_height();
 
-    let state = State::new(
+    // The board goes at the top of the window with the rack below it. Everything is scaled about
+    // the rack's corner, so the rack goes however far below the board that works out to.
+    let mut state = State::new(
         window_width / 2.0 - rack_width / 2.0,
-        window_height / 2.0 - rack_height / 2.0,
+        BOARD_MARGIN + (board_height + BOARD_SPACING) * rack_scale,
         rack_str.as_str(),
+        // Only used to shuffle the bag, so the time is random enough
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64),
     );
+    state.rack.set_drag_smoothing(options.drag_smoothing);
+    // Large print leaves out the decorative jitter so tiles are as easy to read as possible
+    state.rack.set_jitter(options.jitter && !options.large_print);
+    state.rack.set_high_contrast(options.large_print);
+    state.rack_scale = rack_scale;
+    state.rack.set_animation_speed(options.animation_speed);
+    state.rack.set_easing(options.easing);
+    state.board.set_animation_speed(options.animation_speed);
+    state.rack.set_label_style(options.label_style);
+    state.idle_timeout = options.idle_timeout;
+    state.low_power = options.low_power;
+    state.fps_cap = options.fps_cap;
+    state.rack.set_swap_on_drop(options.swap_on_drop);
+    state.crash_report = crash::take_report();
+    // Off by default, since grabbing the screen is slow and the frames take up memory
+    if options.capture {
+        state.frame_capture = Some(capture::FrameCapture::new());
+    }
     ggez::event::run(ctx, event_loop, state);
 }
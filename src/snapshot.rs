@@ -0,0 +1,135 @@
+//! Snapshots of where every tile is, and the differences between two of them, for keeping
+//! something else (e.g. another copy of the game, or a log) in step with a rack and board
+
+use crate::{letter_value, Board, TileRack};
+
+/// Where a tile is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Place {
+    /// In the rack slot with this index
+    Rack(usize),
+    Board { column: usize, row: usize },
+}
+
+/// Something that happened to a tile between two snapshots
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    /// A tile appeared, e.g. drawn from the bag
+    TileAdded { id: usize, letter: char, to: Place },
+    /// A tile disappeared, e.g. put back in the bag
+    TileRemoved { id: usize, letter: char, from: Place },
+    TileMoved { id: usize, from: Place, to: Place },
+    /// The total value of the tiles on the board went from one number to another
+    ScoreChanged { from: u32, to: u32 },
+}
+
+/// Every tile on a rack and board at one moment, identified by tile id. Tiles are recorded by
+/// the slot or cell they belong in, not where they happen to be drawn mid-animation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateSnapshot {
+    // Tile ids, letters and places, rack first and then the board row by row
+    tiles: Vec<(usize, char, Place)>,
+    // Total value of the tiles on the board
+    score: u32,
+}
+
+impl StateSnapshot {
+    pub fn capture(rack: &TileRack, board: &Board) -> StateSnapshot {
+        let rack_tiles = rack.tiles().iter().enumerate().map(
+            |(index, tile)| (tile.id(), tile.letter(), Place::Rack(index))
+        );
+        let board_tiles = (0..board.rows()).flat_map(
+            |row| (0..board.columns()).filter_map(
+                move |column| board.get_tile(column, row)
+                    .map(|tile| (tile.id(), tile.letter(), Place::Board { column, row }))
+            )
+        );
+        let tiles: Vec<(usize, char, Place)> = rack_tiles.chain(board_tiles).collect();
+        let score = tiles.iter()
+            .filter(|(_, _, place)| matches!(place, Place::Board { .. }))
+            .map(|(_, letter, _)| letter_value(*letter))
+            .sum();
+        StateSnapshot { tiles, score }
+    }
+
+    /// Total value of the tiles on the board
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    /// Where the tile with this id is, if it's in the snapshot
+    pub fn find(&self, id: usize) -> Option<Place> {
+        self.tiles.iter().find(|(tile_id, _, _)| *tile_id == id).map(|(_, _, place)| *place)
+    }
+}
+
+/// Lists what changed to get from `before` to `after`: removed tiles first, then moved tiles,
+/// then added tiles, each in the order they appear in their snapshot, and finally the score if
+/// it changed
+pub fn diff(before: &StateSnapshot, after: &StateSnapshot) -> Vec<Change> {
+    let removed = before.tiles.iter()
+        .filter(|(id, _, _)| after.find(*id).is_none())
+        .map(|(id, letter, from)| Change::TileRemoved { id: *id, letter: *letter, from: *from });
+    let moved = before.tiles.iter().filter_map(
+        |(id, _, from)| match after.find(*id) {
+            Some(to) if to != *from => Some(Change::TileMoved { id: *id, from: *from, to }),
+            _ => None,
+        }
+    );
+    let added = after.tiles.iter()
+        .filter(|(id, _, _)| before.find(*id).is_none())
+        .map(|(id, letter, to)| Change::TileAdded { id: *id, letter: *letter, to: *to });
+    let score = Some(Change::ScoreChanged { from: before.score, to: after.score })
+        .filter(|_| before.score != after.score);
+    removed.chain(moved).chain(added).chain(score).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(tiles: &[(usize, char, Place)], score: u32) -> StateSnapshot {
+        StateSnapshot { tiles: tiles.to_vec(), score }
+    }
+
+    #[test]
+    fn identical_snapshots_have_no_changes() {
+        let before = snapshot(&[(0, 'A', Place::Rack(0)), (1, 'B', Place::Rack(1))], 0);
+        assert_eq!(diff(&before, &before.clone()), Vec::new());
+    }
+
+    #[test]
+    fn added_and_removed_tiles() {
+        let before = snapshot(&[(0, 'A', Place::Rack(0)), (1, 'B', Place::Rack(1))], 0);
+        let after = snapshot(&[(0, 'A', Place::Rack(0)), (2, 'C', Place::Rack(1))], 0);
+        assert_eq!(
+            diff(&before, &after),
+            vec![
+                Change::TileRemoved { id: 1, letter: 'B', from: Place::Rack(1) },
+                Change::TileAdded { id: 2, letter: 'C', to: Place::Rack(1) },
+            ],
+        );
+    }
+
+    #[test]
+    fn moved_tiles_and_score() {
+        let before = snapshot(&[(0, 'A', Place::Rack(0)), (1, 'Q', Place::Rack(1))], 0);
+        let after = snapshot(&[(0, 'A', Place::Rack(0)), (1, 'Q', Place::Board { column: 2, row: 3 })], 10);
+        assert_eq!(
+            diff(&before, &after),
+            vec![
+                Change::TileMoved { id: 1, from: Place::Rack(1), to: Place::Board { column: 2, row: 3 } },
+                Change::ScoreChanged { from: 0, to: 10 },
+            ],
+        );
+    }
+
+    #[test]
+    fn capture_records_rack_slots() {
+        let rack = TileRack::new(0.0, 0.0, "HI", crate::TileRackConfig::default());
+        let board = Board::new(0.0, 0.0, 2, 2, crate::TileRackConfig::default());
+        let snapshot = StateSnapshot::capture(&rack, &board);
+        assert_eq!(snapshot.find(rack.tiles()[1].id()), Some(Place::Rack(1)));
+        assert_eq!(snapshot.score(), 0);
+    }
+}
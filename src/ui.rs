@@ -0,0 +1,240 @@
+use std::time::Duration;
+use ggez::graphics::Color;
+use mint::Point2;
+
+use crate::icon::Icon;
+
+const MENU_ITEM_WIDTH: f32 = 200.0;
+const MENU_ITEM_HEIGHT: f32 = 28.0;
+const MENU_PADDING: f32 = 8.0;
+const MENU_ICON_SIZE: f32 = 16.0;
+const MENU_COLOUR: Color = Color::new(0.98, 0.98, 0.98, 1.0);
+const MENU_BORDER_COLOUR: Color = Color::new(0.6, 0.6, 0.6, 1.0);
+const MENU_HOVER_COLOUR: Color = Color::new(0.8, 0.87, 1.0, 1.0);
+const LONG_PRESS_DURATION: Duration = Duration::from_millis(600);
+// How far the pointer can drift before a press stops counting as a long press
+const LONG_PRESS_TOLERANCE: f32 = 8.0;
+const LONG_PRESS_RADIUS: f32 = 20.0;
+const LONG_PRESS_COLOUR: Color = Color::new(0.2, 0.4, 0.9, 0.8);
+
+/// A popup list of actions with icons and labels, opened at the mouse cursor. `T` is whatever the owner
+/// wants back when an item is clicked, usually an action enum.
+pub struct ContextMenu<T> {
+    x: f32,
+    y: f32,
+    items: Vec<(Icon, String, T)>,
+    hovered: Option<usize>,
+    blend_mode: Option<ggez::graphics::BlendMode>,
+}
+
+impl<T: Copy> ContextMenu<T> {
+    pub fn new(x: f32, y: f32, items: Vec<(Icon, String, T)>) -> ContextMenu<T> {
+        ContextMenu {
+            x,
+            y,
+            items,
+            hovered: None,
+            blend_mode: None,
+        }
+    }
+
+    fn item_index_at(&self, x: f32, y: f32) -> Option<usize> {
+        let inside_x = self.x <= x && x < self.x + MENU_ITEM_WIDTH;
+        let index = ((y - self.y) / MENU_ITEM_HEIGHT).floor();
+        if inside_x && index >= 0.0 && (index as usize) < self.items.len() {
+            Some(index as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the action of the item at the given point, if there is one
+    pub fn item_at(&self, x: f32, y: f32) -> Option<T> {
+        self.item_index_at(x, y).map(|index| self.items[index].2)
+    }
+
+    /// Highlights the item under the mouse
+    pub fn hover(&mut self, x: f32, y: f32) {
+        self.hovered = self.item_index_at(x, y);
+    }
+
+    /// Moves the highlight to the next (or previous) item for keyboard navigation, wrapping
+    /// around at either end
+    pub fn focus_next(&mut self, forward: bool) {
+        let count = self.items.len();
+        if count == 0 {
+            return;
+        }
+        self.hovered = Some(match self.hovered {
+            Some(index) if forward => (index + 1) % count,
+            Some(index) => (index + count - 1) % count,
+            None if forward => 0,
+            None => count - 1,
+        });
+    }
+
+    /// Returns the action of the highlighted item, if there is one
+    pub fn focused_item(&self) -> Option<T> {
+        self.hovered.map(|index| self.items[index].2)
+    }
+}
+
+impl<T> ggez::graphics::Drawable for ContextMenu<T> {
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        param: ggez::graphics::DrawParam,
+    ) -> ggez::GameResult {
+        let rect = ggez::graphics::Rect::new(
+            self.x, self.y,
+            MENU_ITEM_WIDTH, MENU_ITEM_HEIGHT * self.items.len() as f32,
+        );
+        let background = ggez::graphics::Mesh::new_rectangle(
+            ctx,
+            ggez::graphics::DrawMode::fill(),
+            rect,
+            MENU_COLOUR,
+        )?;
+        ggez::graphics::draw(ctx, &background, ggez::graphics::DrawParam::default())?;
+
+        if let Some(hovered) = self.hovered {
+            let hover_rect = ggez::graphics::Rect::new(
+                self.x, self.y + hovered as f32 * MENU_ITEM_HEIGHT,
+                MENU_ITEM_WIDTH, MENU_ITEM_HEIGHT,
+            );
+            let hover_drawable = ggez::graphics::Mesh::new_rectangle(
+                ctx,
+                ggez::graphics::DrawMode::fill(),
+                hover_rect,
+                MENU_HOVER_COLOUR,
+            )?;
+            ggez::graphics::draw(ctx, &hover_drawable, ggez::graphics::DrawParam::default())?;
+        }
+
+        let border = ggez::graphics::Mesh::new_rectangle(
+            ctx,
+            ggez::graphics::DrawMode::stroke(1.0),
+            rect,
+            MENU_BORDER_COLOUR,
+        )?;
+        ggez::graphics::draw(ctx, &border, ggez::graphics::DrawParam::default())?;
+
+        let font = ggez::graphics::Font::default();
+        for (index, (icon, label, action)) in self.items.iter().enumerate() {
+            let item_middle = self.y + (index as f32 + 0.5) * MENU_ITEM_HEIGHT;
+            icon.draw(ctx, self.x + MENU_PADDING, item_middle - MENU_ICON_SIZE / 2.0, MENU_ICON_SIZE, Color::BLACK)?;
+            let text = ggez::graphics::Text::new((label.as_str(), font, 18.0));
+            let text_dimensions = text.dimensions(ctx);
+            let point = Point2 {
+                x: self.x + MENU_PADDING * 2.0 + MENU_ICON_SIZE,
+                y: item_middle - (text_dimensions.h / 2.0),
+            };
+            ggez::graphics::draw(ctx, &text, (point, Color::BLACK))?;
+        }
+        Ok(())
+    }
+
+    fn dimensions(&self, ctx: &mut ggez::Context) -> Option<ggez::graphics::Rect> {
+        Some(
+            ggez::graphics::Rect::new(
+                self.x,
+                self.y,
+                MENU_ITEM_WIDTH,
+                MENU_ITEM_HEIGHT * self.items.len() as f32,
+            )
+        )
+    }
+
+    fn set_blend_mode(&mut self, mode: Option<ggez::graphics::BlendMode>) {
+        self.blend_mode = mode;
+    }
+
+    fn blend_mode(&self) -> Option<ggez::graphics::BlendMode> {
+        self.blend_mode
+    }
+}
+
+/// Tracks a press being held in place, e.g. a finger resting on a tile. Meant to be dropped by
+/// the owner once the pointer moves away or is released.
+pub struct LongPress {
+    x: f32,
+    y: f32,
+    started: Duration,
+    progress: f32,
+}
+
+impl LongPress {
+    pub fn new(x: f32, y: f32, started: Duration) -> LongPress {
+        LongPress {
+            x,
+            y,
+            started,
+            progress: 0.0,
+        }
+    }
+
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn y(&self) -> f32 {
+        self.y
+    }
+
+    /// Whether the pointer is still close enough to where the press started
+    pub fn within_tolerance(&self, x: f32, y: f32) -> bool {
+        (x - self.x).hypot(y - self.y) <= LONG_PRESS_TOLERANCE
+    }
+
+    /// Updates the progress shown by the indicator and returns true once the press has been
+    /// held long enough
+    pub fn update(&mut self, now: Duration) -> bool {
+        let held = now.saturating_sub(self.started);
+        self.progress = (held.as_secs_f32() / LONG_PRESS_DURATION.as_secs_f32()).min(1.0);
+        held >= LONG_PRESS_DURATION
+    }
+}
+
+impl ggez::graphics::Drawable for LongPress {
+    fn draw(
+        &self,
+        ctx: &mut ggez::Context,
+        param: ggez::graphics::DrawParam,
+    ) -> ggez::GameResult {
+        // Radial progress: an arc starting at 12 o'clock that closes as the hold completes
+        let segments = ((self.progress * 32.0) as usize).max(1);
+        let points: Vec<Point2<f32>> = (0..=segments).map(
+            |segment| {
+                let angle = (segment as f32 / 32.0) * std::f32::consts::TAU - std::f32::consts::FRAC_PI_2;
+                Point2 {
+                    x: self.x + LONG_PRESS_RADIUS * angle.cos(),
+                    y: self.y + LONG_PRESS_RADIUS * angle.sin(),
+                }
+            }
+        ).collect();
+        let arc = ggez::graphics::Mesh::new_polyline(
+            ctx,
+            ggez::graphics::DrawMode::stroke(4.0),
+            &points,
+            LONG_PRESS_COLOUR,
+        )?;
+        ggez::graphics::draw(ctx, &arc, ggez::graphics::DrawParam::default())
+    }
+
+    fn dimensions(&self, ctx: &mut ggez::Context) -> Option<ggez::graphics::Rect> {
+        Some(
+            ggez::graphics::Rect::new(
+                self.x - LONG_PRESS_RADIUS,
+                self.y - LONG_PRESS_RADIUS,
+                LONG_PRESS_RADIUS * 2.0,
+                LONG_PRESS_RADIUS * 2.0,
+            )
+        )
+    }
+
+    fn set_blend_mode(&mut self, mode: Option<ggez::graphics::BlendMode>) {}
+
+    fn blend_mode(&self) -> Option<ggez::graphics::BlendMode> {
+        None
+    }
+}